@@ -0,0 +1,30 @@
+use std::{
+    env,
+    path::Path,
+    process::Command,
+};
+
+fn main() {
+    let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let git_dir = Path::new(&dir).join(".git");
+
+    // only use the commit, if this is a checkout of gitlog2rss and not of a
+    // project gitlog2rss is part of
+    if !git_dir.exists() {
+        return;
+    }
+
+    println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+    println!("cargo:rerun-if-changed={}", git_dir.join("refs").display());
+
+    let output = Command::new("git")
+        .args(["-C", &dir, "rev-parse", "--short", "HEAD"])
+        .output();
+
+    if let Ok(output) = output {
+        if output.status.success() {
+            let commit = String::from_utf8_lossy(&output.stdout);
+            println!("cargo:rustc-env=GITLOG2RSS_BUILD_COMMIT={}", commit.trim());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Curated names and homepages of the authors by their email
+
+use crate::Entry;
+use std::{collections::HashMap, error};
+use yaml_rust::Yaml;
+
+struct Author {
+    name: Option<String>,
+    uri: Option<String>,
+}
+
+/// The config entry `authors`, a map of email to name or to a map with name
+/// and uri
+#[derive(Default)]
+pub struct Authors {
+    /// by lower case email
+    map: HashMap<String, Author>,
+}
+
+impl Authors {
+    pub fn from_conf(conf: &Yaml) -> Result<Self, Box<dyn error::Error>> {
+        let list = match conf {
+            Yaml::Hash(list) => list,
+            Yaml::BadValue => return Ok(Authors::default()),
+            _ => return Err("Invalid value of config entry 'authors': expected a map".into()),
+        };
+
+        let mut map = HashMap::new();
+        for (email, value) in list {
+            let email = email.as_str().ok_or("Invalid email in config entry 'authors'")?;
+            let author = match value {
+                Yaml::String(name) => Author { name: Some(name.clone()), uri: None },
+                Yaml::Hash(_) => Author {
+                    name: value["name"].as_str().map(str::to_string),
+                    uri: value["uri"].as_str().map(str::to_string),
+                },
+                _ => return Err(format!("Invalid value for {} in config entry 'authors'", email).into()),
+            };
+            map.insert(email.to_lowercase(), author);
+        }
+
+        Ok(Authors { map })
+    }
+
+    /// Returns the configured name of the author with the email or `name`
+    pub fn name(&self, email: &str, name: &str) -> String {
+        self.map.get(&email.to_lowercase())
+            .and_then(|x| x.name.clone())
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Sets the configured names and homepages of the authors of the entries
+    pub fn apply(&self, entries: &mut [Entry]) {
+        if self.map.is_empty() {
+            return;
+        }
+
+        for e in entries {
+            let Some(author) = self.map.get(&e.author_email.to_lowercase()) else { continue };
+            if let Some(ref name) = author.name {
+                e.author_name = name.clone();
+                if e.item.author().is_some() {
+                    e.item.set_author(format!("{} ({})", e.author_email, name));
+                }
+            }
+            e.author_uri = author.uri.clone();
+        }
+    }
+}
+
+/// Returns the email like `user at example dot org`
+pub fn obfuscate_email(email: &str) -> String {
+    email.replace('@', " at ").replace('.', " dot ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, email: &str) -> Entry {
+        Entry {
+            time: git2::Time::new(0, 0),
+            commit: git2::Oid::zero(),
+            status: git2::Delta::Added,
+            path: String::new(),
+            page: None,
+            author_name: name.to_string(),
+            author_email: email.to_string(),
+            author_uri: None,
+            item: rss::ItemBuilder::default()
+                .author(Some(format!("{} ({})", email, name)))
+                .build(),
+        }
+    }
+
+    #[test]
+    fn names_and_uris() {
+        let conf = &yaml_rust::YamlLoader::load_from_str("
+JO@jo-so.de: Jörg Sommer
+jo@example.org: {name: Jo, uri: 'https://jo-so.de/'}
+").unwrap()[0];
+        let authors = Authors::from_conf(conf).unwrap();
+        assert_eq!(authors.name("jo@JO-SO.de", "joerg"), "Jörg Sommer");
+        assert_eq!(authors.name("ann@example.org", "Ann"), "Ann");
+
+        let mut entries = vec![entry("joerg", "jo@example.org"), entry("Ann", "ann@example.org")];
+        authors.apply(&mut entries);
+        assert_eq!(entries[0].author_name, "Jo");
+        assert_eq!(entries[0].author_uri.as_deref(), Some("https://jo-so.de/"));
+        assert_eq!(entries[0].item.author(), Some("jo@example.org (Jo)"));
+        assert_eq!(entries[1].author_name, "Ann");
+        assert_eq!(entries[1].author_uri, None);
+
+        assert!(Authors::from_conf(&Yaml::Integer(1)).is_err());
+        assert!(Authors::from_conf(&yaml_rust::YamlLoader::load_from_str("a@b: 1").unwrap()[0]).is_err());
+    }
+
+    #[test]
+    fn obfuscated_email() {
+        assert_eq!(obfuscate_email("joerg@jo-so.de"), "joerg at jo-so dot de");
+    }
+}
@@ -0,0 +1,135 @@
+//! Reading of the content of files for items
+
+use crate::load_blob;
+use git2::{Oid, Repository};
+use log::{debug, warn};
+use std::{
+    error,
+    fs,
+    path::PathBuf,
+};
+use yaml_rust::Yaml;
+
+enum Source {
+    /// the blob of the commit of the item
+    Blob,
+    /// the file in a checkout
+    Worktree(PathBuf),
+    /// the file in the tree of a fixed commit
+    Tree(Oid),
+}
+
+pub struct Reader {
+    source: Source,
+    max_size: Option<usize>,
+}
+
+impl Reader {
+    /// Reads the config entries `content-source`, `worktree` and `content-rev`
+    pub fn from_conf(conf: &Yaml, repo: &Repository, max_size: Option<usize>)
+                     -> Result<Self, Box<dyn error::Error>>
+    {
+        let source = match conf["content-source"].as_str() {
+            None | Some("blob") => Source::Blob,
+
+            Some("worktree") => {
+                let dir = match conf["worktree"].as_str() {
+                    Some(dir) => PathBuf::from(dir),
+                    None => repo.workdir()
+                        .ok_or("Config entry 'worktree' is required for a bare repository")?
+                        .to_path_buf(),
+                };
+                debug!("Reading content from worktree {}", dir.display());
+                Source::Worktree(dir)
+            }
+
+            Some("rev") => {
+                let rev = conf["content-rev"].as_str().ok_or("Config entry 'content-rev' is missing")?;
+                let tree = repo.revparse_single(rev)?.peel_to_tree()?;
+                debug!("Reading content from tree {} of {}", tree.id(), rev);
+                Source::Tree(tree.id())
+            }
+
+            Some(x) => return Err(format!("Invalid value of config entry 'content-source': {}", x).into()),
+        };
+
+        Ok(Reader { source, max_size })
+    }
+
+    /// Reads the content of the file `path` whose blob in the commit of the
+    /// item is `blob`; gives `None`, if the file is missing or too large
+    pub fn read(&self, repo: &Repository, path: &str, blob: Oid)
+                -> Result<Option<Vec<u8>>, Box<dyn error::Error>>
+    {
+        let id = match self.source {
+            Source::Blob => blob,
+
+            Source::Worktree(ref dir) => {
+                let file = dir.join(path);
+                let len = match fs::metadata(&file) {
+                    Ok(meta) => meta.len(),
+                    Err(err) => {
+                        debug!("Can't read {}: {}", file.display(), err);
+                        return Ok(None);
+                    }
+                };
+
+                if self.max_size.is_some_and(|max| len > max as u64) {
+                    warn!("Skipping file {} with {} bytes, because it is larger than max-blob-size",
+                          file.display(), len);
+                    return Ok(None);
+                }
+
+                return Ok(Some(fs::read(&file)?));
+            }
+
+            Source::Tree(tree) => match repo.find_tree(tree)?.get_path(path.as_ref()) {
+                Ok(entry) => entry.id(),
+                Err(_) => {
+                    debug!("File {} is missing in the tree for content", path);
+                    return Ok(None);
+                }
+            },
+        };
+
+        Ok(load_blob(repo, id, self.max_size)?.map(|blob| blob.content().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    #[test]
+    fn sources() {
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-content-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+
+        let old = repo.blob(b"# Alt\n").unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("a.md", repo.blob(b"# Deploy\n").unwrap(), 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let sig = git2::Signature::now("Ann Author", "ann@example.org").unwrap();
+        let commit = repo.commit(None, &sig, &sig, "Deploy", &tree, &[]).unwrap();
+        repo.reference("refs/heads/deploy", commit, false, "").unwrap();
+        fs::write(dir.join("a.md"), "# Worktree\n").unwrap();
+
+        let read = |conf: &str, path: &str| {
+            let conf = YamlLoader::load_from_str(conf).unwrap().remove(0);
+            Reader::from_conf(&conf, &repo, Some(32)).unwrap().read(&repo, path, old).unwrap()
+        };
+
+        assert_eq!(read("{}", "a.md").as_deref(), Some(&b"# Alt\n"[..]));
+        assert_eq!(read("content-source: worktree", "a.md").as_deref(), Some(&b"# Worktree\n"[..]));
+        assert_eq!(read("content-source: worktree", "b.md"), None);
+        assert_eq!(read("{content-source: rev, content-rev: deploy}", "a.md").as_deref(), Some(&b"# Deploy\n"[..]));
+        assert_eq!(read("{content-source: rev, content-rev: deploy}", "b.md"), None);
+
+        fs::write(dir.join("a.md"), "# Worktree with a text above max-blob-size\n").unwrap();
+        assert_eq!(read("content-source: worktree", "a.md"), None);
+        assert!(Reader::from_conf(&YamlLoader::load_from_str("content-source: x").unwrap()[0], &repo, None).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+//! HTML of the changes of a file for the description of items
+
+use crate::html_escape;
+use git2::Patch;
+use std::error;
+
+const INS_STYLE: &str = "background:#e6ffec;text-decoration:none";
+const DEL_STYLE: &str = "background:#ffebe9;text-decoration:none";
+/// the number of words and spaces around the changes in the word diff
+const CONTEXT_WORDS: usize = 12;
+
+/// Returns the patch as `<pre>` with the added lines in `<ins>` and the
+/// removed lines in `<del>`; after `max_lines` lines, the rest is left out
+pub fn html(patch: &Patch, max_lines: usize) -> Result<String, Box<dyn error::Error>> {
+    let mut out = String::from("<pre class=\"diff\">");
+    let mut count = 0;
+
+    'hunks: for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, lines) = patch.hunk(hunk_idx)?;
+        out.push_str(&html_escape(String::from_utf8_lossy(hunk.header()).trim_end()));
+        out.push('\n');
+
+        for line_idx in 0..lines {
+            if count == max_lines {
+                out.push_str("…\n");
+                break 'hunks;
+            }
+            count += 1;
+
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+            let text = html_escape(String::from_utf8_lossy(line.content()).trim_end_matches('\n'));
+            match line.origin() {
+                '+' => out.push_str(&format!("<ins style=\"{}\">+{}</ins>\n", INS_STYLE, text)),
+                '-' => out.push_str(&format!("<del style=\"{}\">-{}</del>\n", DEL_STYLE, text)),
+                ' ' => out.push_str(&format!(" {}\n", text)),
+                // markers like "\ No newline at end of file"
+                _ => {}
+            }
+        }
+    }
+
+    out.push_str("</pre>");
+    Ok(out)
+}
+
+/// Returns the changed passages between the texts with the removed words in
+/// `<del>` and the added words in `<ins>`; each passage is a paragraph with a
+/// few words around the changes; after `max_passages`, the rest is left out;
+/// changes of whitespace, like the reflow of a paragraph, are ignored
+pub fn words(old: &str, new: &str, max_passages: usize) -> String {
+    use similar::{ChangeTag, TextDiff};
+
+    let normalize = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (old, new) = (normalize(old), normalize(new));
+    let diff = TextDiff::from_words(&old, &new);
+    let groups = diff.grouped_ops(CONTEXT_WORDS);
+
+    let mut out = String::new();
+    for (idx, group) in groups.iter().enumerate() {
+        if idx == max_passages {
+            out.push_str("<p>…</p>");
+            break;
+        }
+
+        out.push_str("<p>");
+        if group.first().is_some_and(|op| op.old_range().start > 0) {
+            out.push('…');
+        }
+
+        let mut open: Option<ChangeTag> = None;
+        for change in group.iter().flat_map(|op| diff.iter_changes(op)) {
+            if open != Some(change.tag()) {
+                match open {
+                    Some(ChangeTag::Delete) => out.push_str("</del>"),
+                    Some(ChangeTag::Insert) => out.push_str("</ins>"),
+                    _ => {}
+                }
+                match change.tag() {
+                    ChangeTag::Delete => out.push_str(&format!("<del style=\"{}\">", DEL_STYLE)),
+                    ChangeTag::Insert => out.push_str(&format!("<ins style=\"{}\">", INS_STYLE)),
+                    ChangeTag::Equal => {}
+                }
+                open = Some(change.tag());
+            }
+            out.push_str(&html_escape(change.value()));
+        }
+        match open {
+            Some(ChangeTag::Delete) => out.push_str("</del>"),
+            Some(ChangeTag::Insert) => out.push_str("</ins>"),
+            _ => {}
+        }
+
+        if group.last().is_some_and(|op| diff.old_slice(op.old_range().end).is_some()) {
+            out.push('…');
+        }
+        out.push_str("</p>");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_diff() {
+        let html = words("Das ist ein Test.", "Das ist ein  neuer\nTest.", 3);
+        assert_eq!(html, format!("<p>Das ist ein <ins style=\"{}\">neuer </ins>Test.</p>", INS_STYLE));
+
+        let html = words("a < b", "a > b", 3);
+        assert!(html.contains(&format!("<del style=\"{}\">&lt;</del>", DEL_STYLE)), "{}", html);
+
+        // reflowed paragraphs have no changes
+        assert_eq!(words("one two\nthree", "one\ntwo three", 3), "");
+
+        let old = (0..100).map(|x| x.to_string()).collect::<Vec<_>>().join(" ");
+        let new = old.replace("10 ", "ten ").replace("60 ", "sixty ");
+        let html = words(&old, &new, 1);
+        assert!(html.starts_with("<p>…"), "{}", html);
+        assert!(html.ends_with("…</p><p>…</p>"), "{}", html);
+        assert!(!html.contains("sixty"), "{}", html);
+    }
+}
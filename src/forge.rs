@@ -0,0 +1,427 @@
+//! Queries of the API of GitHub or GitLab for information about commits
+
+use git2::Oid;
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    env,
+    error,
+    fs,
+    path::PathBuf,
+};
+use yaml_rust::Yaml;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    GitHub,
+    GitLab,
+}
+
+/// The comments of a commit
+#[derive(Clone, Debug)]
+pub struct Comments {
+    pub count: usize,
+    /// the number of reactions on the comments; GitLab has none for comments
+    /// on commits
+    pub reactions: usize,
+    /// the page of the commit with the discussion
+    pub url: String,
+}
+
+impl Cached for Comments {
+    fn to_json(&self) -> Value {
+        json!({"count": self.count, "reactions": self.reactions, "url": self.url})
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Comments {
+            count: value["count"].as_u64()? as usize,
+            reactions: value["reactions"].as_u64()? as usize,
+            url: value["url"].as_str()?.to_string(),
+        })
+    }
+}
+
+impl Cached for PullRequest {
+    fn to_json(&self) -> Value {
+        json!({"url": self.url, "title": self.title, "description": self.description})
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(PullRequest {
+            url: value["url"].as_str()?.to_string(),
+            title: value["title"].as_str()?.to_string(),
+            description: value["description"].as_str().map(str::to_string),
+        })
+    }
+}
+
+/// A known absence, like a commit without pull request
+impl<T: Cached> Cached for Option<T> {
+    fn to_json(&self) -> Value {
+        self.as_ref().map_or_else(|| json!({"none": true}), T::to_json)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        if value["none"] == true {
+            Some(None)
+        } else {
+            T::from_json(value).map(Some)
+        }
+    }
+}
+
+/// The pull or merge request a commit landed with
+#[derive(Clone, Debug)]
+pub struct PullRequest {
+    pub url: String,
+    pub title: String,
+    pub description: Option<String>,
+}
+
+/// An answer of the API, which can be kept in the cache file
+trait Cached: Sized {
+    fn to_json(&self) -> Value;
+    fn from_json(value: &Value) -> Option<Self>;
+}
+
+/// The answers of the API by commit with the time they were fetched; `None`
+/// for failed queries, which are not kept in the cache file
+type Answers<T> = RefCell<HashMap<Oid, (i64, Option<T>)>>;
+
+pub struct Forge {
+    kind: Kind,
+    api_url: String,
+    web_url: String,
+    /// `owner/name` on GitHub, the path of the project on GitLab
+    repo: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+    /// the file to keep the answers between runs
+    cache_file: Option<PathBuf>,
+    /// the age in seconds after which the answers get fetched again
+    max_age: i64,
+    /// whether there are answers not yet in the cache file
+    changed: Cell<bool>,
+    comments: Answers<Comments>,
+    pulls: Answers<Option<PullRequest>>,
+}
+
+impl Forge {
+    /// Reads the config block `forge`
+    pub fn from_conf(conf: &Yaml) -> Result<Option<Self>, Box<dyn error::Error>> {
+        if conf.is_badvalue() {
+            return Ok(None);
+        }
+
+        let kind = match conf["type"].as_str() {
+            Some("github") => Kind::GitHub,
+            Some("gitlab") => Kind::GitLab,
+            _ => return Err("Config entry 'forge.type' must be github or gitlab".into()),
+        };
+
+        let repo = conf["repo"].as_str()
+            .ok_or("Config entry 'forge.repo' is missing")?
+            .trim_matches('/')
+            .to_string();
+
+        let (api_url, web_url) = match kind {
+            Kind::GitHub => ("https://api.github.com", "https://github.com"),
+            Kind::GitLab => ("https://gitlab.com/api/v4", "https://gitlab.com"),
+        };
+
+        let token = conf["token-env"].as_str()
+            .and_then(|var| env::var(var).ok())
+            .filter(|x| !x.is_empty());
+
+        let max_age = match &conf["cache-max-age"] {
+            Yaml::String(x) => humantime::parse_duration(x)?.as_secs() as i64,
+            Yaml::BadValue => 86400,
+            _ => return Err("Invalid value of config entry 'forge.cache-max-age'".into()),
+        };
+
+        let forge = Forge {
+            kind,
+            api_url: conf["api-url"].as_str().unwrap_or(api_url).trim_end_matches('/').to_string(),
+            web_url: conf["web-url"].as_str().unwrap_or(web_url).trim_end_matches('/').to_string(),
+            repo,
+            token,
+            agent: ureq::AgentBuilder::new()
+                .user_agent(concat!(clap::crate_name!(), "/", clap::crate_version!()))
+                .build(),
+            cache_file: conf["cache-file"].as_str().map(PathBuf::from),
+            max_age,
+            changed: Cell::new(false),
+            comments: Default::default(),
+            pulls: Default::default(),
+        };
+        forge.load();
+
+        Ok(Some(forge))
+    }
+
+    /// Reads the answers of earlier runs from the cache file, if it exists
+    fn load(&self) {
+        let Some(ref file) = self.cache_file else { return };
+        let cache = match fs::read(file) {
+            Ok(data) => match serde_json::from_slice::<Value>(&data) {
+                Ok(cache) => cache,
+                Err(err) => {
+                    warn!("Ignoring the forge cache {}: {}", file.display(), err);
+                    return;
+                }
+            },
+            Err(_) => return,
+        };
+
+        let min_time = chrono::Utc::now().timestamp() - self.max_age;
+        load_answers(&cache["comments"], min_time, &self.comments);
+        load_answers(&cache["pulls"], min_time, &self.pulls);
+        debug!("Read {} answers from the forge cache {}",
+               self.comments.borrow().len() + self.pulls.borrow().len(), file.display());
+    }
+
+    /// Writes the answers to the cache file, if there are new ones
+    pub fn save(&self) -> Result<(), Box<dyn error::Error>> {
+        let Some(ref file) = self.cache_file else { return Ok(()) };
+        if !self.changed.get() {
+            return Ok(());
+        }
+
+        let cache = json!({
+            "comments": save_answers(&self.comments),
+            "pulls": save_answers(&self.pulls),
+        });
+
+        info!("Writing the forge cache {}", file.display());
+        fs::write(file, serde_json::to_vec(&cache)?)
+            .map_err(|err| format!("Failed to write the forge cache {}: {}", file.display(), err))?;
+        self.changed.set(false);
+        Ok(())
+    }
+
+    fn get(&self, path: &str) -> Result<Value, Box<dyn error::Error>> {
+        Ok(self.request(&format!("{}/{}", self.api_url, path))?.into_json()?)
+    }
+
+    fn request(&self, url: &str) -> Result<ureq::Response, Box<dyn error::Error>> {
+        debug!("Querying {}", url);
+
+        let mut req = self.agent.get(url);
+        req = match (self.kind, &self.token) {
+            (Kind::GitHub, Some(token)) => req.set("Authorization", &format!("Bearer {}", token)),
+            (Kind::GitLab, Some(token)) => req.set("PRIVATE-TOKEN", token),
+            (_, None) => req,
+        };
+        if self.kind == Kind::GitHub {
+            req = req.set("Accept", "application/vnd.github+json");
+        }
+
+        Ok(req.call()?)
+    }
+
+    /// Fetches all pages of the list by the links `rel="next"` of the header
+    /// `Link`, which both GitHub and GitLab send
+    fn get_all(&self, path: &str) -> Result<Vec<Value>, Box<dyn error::Error>> {
+        let mut list = Vec::new();
+        let mut url = Some(format!("{}/{}", self.api_url, path));
+
+        while let Some(current) = url {
+            let resp = self.request(&current)?;
+            url = resp.header("link").and_then(next_link);
+            match resp.into_json()? {
+                Value::Array(page) => list.extend(page),
+                _ => return Err("Unexpected answer of the forge API: expected a list".into()),
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// The path of the project in API URLs
+    fn project(&self) -> String {
+        match self.kind {
+            Kind::GitHub => format!("repos/{}", self.repo),
+            Kind::GitLab => format!("projects/{}", self.repo.replace('/', "%2F")),
+        }
+    }
+
+    /// The web page of the commit
+    pub fn commit_url(&self, commit: Oid) -> String {
+        match self.kind {
+            Kind::GitHub => format!("{}/{}/commit/{}", self.web_url, self.repo, commit),
+            Kind::GitLab => format!("{}/{}/-/commit/{}", self.web_url, self.repo, commit),
+        }
+    }
+
+    /// Fetches the number of comments on the commit and their reactions;
+    /// errors of the API are logged and give `None`
+    pub fn comments(&self, commit: Oid) -> Option<Comments> {
+        if let Some((_, cached)) = self.comments.borrow().get(&commit) {
+            return cached.clone();
+        }
+
+        let path = match self.kind {
+            Kind::GitHub => format!("{}/commits/{}/comments?per_page=100", self.project(), commit),
+            Kind::GitLab => format!("{}/repository/commits/{}/comments?per_page=100", self.project(), commit),
+        };
+
+        let comments = match self.get_all(&path) {
+            Ok(list) => Some(Comments {
+                count: list.len(),
+                reactions: list.iter()
+                    .filter_map(|x| x["reactions"]["total_count"].as_u64())
+                    .sum::<u64>() as usize,
+                url: self.commit_url(commit),
+            }),
+            Err(err) => {
+                warn!("Failed to query comments of commit {}: {}", commit, err);
+                None
+            }
+        };
+
+        self.changed.set(true);
+        self.comments.borrow_mut().insert(commit, (chrono::Utc::now().timestamp(), comments.clone()));
+        comments
+    }
+
+    /// Looks up the pull request (GitHub) or merge request (GitLab) of the
+    /// commit; errors of the API are logged and give `None`
+    pub fn pull_request(&self, commit: Oid) -> Option<PullRequest> {
+        if let Some((_, cached)) = self.pulls.borrow().get(&commit) {
+            return cached.clone().flatten();
+        }
+
+        let (path, url_key, desc_key) = match self.kind {
+            Kind::GitHub => (format!("{}/commits/{}/pulls", self.project(), commit), "html_url", "body"),
+            Kind::GitLab => (
+                format!("{}/repository/commits/{}/merge_requests", self.project(), commit),
+                "web_url",
+                "description",
+            ),
+        };
+
+        let pull = match self.get(&path) {
+            Ok(Value::Array(list)) => Some(pick_pull_request(&list, url_key, desc_key)),
+            Ok(_) => {
+                warn!("Unexpected answer of the forge API for pull requests of commit {}", commit);
+                None
+            }
+            Err(err) => {
+                warn!("Failed to query pull requests of commit {}: {}", commit, err);
+                None
+            }
+        };
+
+        self.changed.set(true);
+        self.pulls.borrow_mut().insert(commit, (chrono::Utc::now().timestamp(), pull.clone()));
+        pull.flatten()
+    }
+}
+
+/// Picks the request that got merged or else the first one of the list
+fn pick_pull_request(list: &[Value], url_key: &str, desc_key: &str) -> Option<PullRequest> {
+    let merged = list.iter().find(|x| !x["merged_at"].is_null());
+    merged.or_else(|| list.first()).and_then(|pr| {
+        Some(PullRequest {
+            url: pr[url_key].as_str()?.to_string(),
+            title: pr["title"].as_str()?.to_string(),
+            description: pr[desc_key].as_str().filter(|x| !x.trim().is_empty()).map(str::to_string),
+        })
+    })
+}
+
+fn load_answers<T: Cached>(cache: &Value, min_time: i64, answers: &Answers<T>) {
+    let mut answers = answers.borrow_mut();
+    for (commit, value) in cache.as_object().into_iter().flatten() {
+        let time = value["time"].as_i64().unwrap_or(0);
+        if let (Ok(commit), Some(info), true) = (Oid::from_str(commit), T::from_json(value), time >= min_time) {
+            answers.insert(commit, (time, Some(info)));
+        }
+    }
+}
+
+fn save_answers<T: Cached>(answers: &Answers<T>) -> Map<String, Value> {
+    answers.borrow().iter()
+        .filter_map(|(commit, (time, info))| {
+            let mut value = info.as_ref()?.to_json();
+            value["time"] = (*time).into();
+            Some((commit.to_string(), value))
+        })
+        .collect()
+}
+
+/// Returns the URL of the link `rel="next"` of the header `Link` like
+/// `<https://…?page=2>; rel="next", <https://…?page=5>; rel="last"`
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params.split(';')
+            .any(|x| matches!(x.trim(), "rel=\"next\"" | "rel=next"))
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_links() {
+        let header = "<https://api.github.com/repositories/1/comments?page=2>; rel=\"next\", \
+                      <https://api.github.com/repositories/1/comments?page=5>; rel=\"last\"";
+        assert_eq!(next_link(header).as_deref(), Some("https://api.github.com/repositories/1/comments?page=2"));
+
+        let header = "<https://gitlab.com/api/v4/projects/1/comments?page=1>; rel=\"first\", \
+                      <https://gitlab.com/api/v4/projects/1/comments?page=3>; rel=\"last\"";
+        assert_eq!(next_link(header), None);
+    }
+
+    #[test]
+    fn cache_file() {
+        let file = env::temp_dir().join(format!("gitlog2rss-forge-{}.json", std::process::id()));
+        let conf = |age: &str| yaml_rust::YamlLoader::load_from_str(&format!(
+            "{{type: github, repo: jo-so/website, cache-file: '{}', cache-max-age: {}}}", file.display(), age
+        )).unwrap().remove(0);
+        let commit = Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+
+        let forge = Forge::from_conf(&conf("1d")).unwrap().unwrap();
+        forge.comments.borrow_mut().insert(commit, (chrono::Utc::now().timestamp() - 3600, Some(Comments {
+            count: 2,
+            reactions: 3,
+            url: forge.commit_url(commit),
+        })));
+        forge.changed.set(true);
+        forge.save().unwrap();
+
+        // the cached answer needs no query of the API
+        let info = Forge::from_conf(&conf("1d")).unwrap().unwrap().comments(commit).unwrap();
+        assert_eq!((info.count, info.reactions), (2, 3));
+        assert_eq!(info.url, "https://github.com/jo-so/website/commit/0123456789abcdef0123456789abcdef01234567");
+
+        // expired answers get dropped
+        assert!(Forge::from_conf(&conf("1m")).unwrap().unwrap().comments.borrow().is_empty());
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn pull_requests() {
+        let list: Vec<Value> = serde_json::from_str(r#"[
+            {"html_url": "https://github.com/o/r/pull/1", "title": "Open", "body": "", "merged_at": null},
+            {"html_url": "https://github.com/o/r/pull/2", "title": "Merged", "body": "Text", "merged_at": "2024-01-01T00:00:00Z"}
+        ]"#).unwrap();
+        let pr = pick_pull_request(&list, "html_url", "body").unwrap();
+        assert_eq!((pr.url.as_str(), pr.title.as_str()), ("https://github.com/o/r/pull/2", "Merged"));
+        assert_eq!(pr.description.as_deref(), Some("Text"));
+
+        let pr = pick_pull_request(&list[..1], "html_url", "body").unwrap();
+        assert_eq!((pr.title.as_str(), pr.description), ("Open", None));
+        assert!(pick_pull_request(&[], "html_url", "body").is_none());
+
+        // a commit without request is cached as well
+        let none: Option<PullRequest> = None;
+        assert!(matches!(Option::<PullRequest>::from_json(&none.to_json()), Some(None)));
+    }
+}
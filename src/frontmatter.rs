@@ -0,0 +1,109 @@
+//! The YAML front matter between `---` lines at the beginning of pages
+
+use log::warn;
+use yaml_rust::{Yaml, YamlLoader};
+
+/// Splits the text into the YAML of the front matter, if any, and the rest
+pub fn split(text: &str) -> (Option<&str>, &str) {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return (None, text);
+    };
+
+    let mut pos = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..pos]), &rest[pos + line.len()..]);
+        }
+        pos += line.len();
+    }
+
+    // no end of the front matter
+    (None, text)
+}
+
+/// Returns the data of the front matter or `Yaml::BadValue` without one
+pub fn parse(text: &str, path: &str) -> Yaml {
+    let Some(yaml) = split(text).0 else {
+        return Yaml::BadValue;
+    };
+
+    match YamlLoader::load_from_str(yaml) {
+        Ok(mut docs) => docs.pop().unwrap_or(Yaml::BadValue),
+        Err(err) => {
+            warn!("Ignoring invalid front matter of {}: {}", path, err);
+            Yaml::BadValue
+        }
+    }
+}
+
+/// Returns the values of the list or the comma-separated string of the key
+pub fn list(data: &Yaml, key: &str) -> Vec<String> {
+    let value = |x: &Yaml| match x {
+        Yaml::String(x) => Some(x.trim().to_string()),
+        Yaml::Integer(x) => Some(x.to_string()),
+        Yaml::Real(x) => Some(x.clone()),
+        _ => None,
+    };
+
+    match &data[key] {
+        Yaml::Array(list) => list.iter().filter_map(value).filter(|x| !x.is_empty()).collect(),
+        Yaml::String(text) => text.split(',').map(str::trim).filter(|x| !x.is_empty()).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns latitude and longitude of the keys `lat` and `lon` or of the key
+/// `location` as map with these keys or as string like `52.52, 13.40`
+pub fn point(data: &Yaml) -> Option<(f64, f64)> {
+    let number = |x: &Yaml| match x {
+        Yaml::Real(_) => x.as_f64(),
+        Yaml::Integer(x) => Some(*x as f64),
+        Yaml::String(x) => x.trim().parse().ok(),
+        _ => None,
+    };
+    let pair = |x: &Yaml| Some((number(&x["lat"])?, number(&x["lon"])?));
+
+    let (lat, lon) = match &data["location"] {
+        Yaml::String(text) => {
+            let (lat, lon) = text.split_once(',').or_else(|| text.trim().split_once(' '))?;
+            (lat.trim().parse().ok()?, lon.trim().parse().ok()?)
+        }
+        location @ Yaml::Hash(_) => pair(location)?,
+        _ => pair(data)?,
+    };
+
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn front_matter() {
+        assert_eq!(split("---\ntitle: A\n---\n# A\n"), (Some("title: A\n"), "# A\n"));
+        assert_eq!(split("---\r\ntitle: A\r\n...\r\nText"), (Some("title: A\r\n"), "Text"));
+        assert_eq!(split("---\ntitle: A\n"), (None, "---\ntitle: A\n"));
+        assert_eq!(split("# A\n---\n"), (None, "# A\n---\n"));
+
+        let data = parse("---\ntitle: A\ntags: [rust, ' git ', '']\n---\n", "a.md");
+        assert_eq!(data["title"].as_str(), Some("A"));
+        assert_eq!(list(&data, "tags"), ["rust", "git"]);
+        assert!(parse("---\ntitle: [\n---\n", "a.md").is_badvalue());
+
+        let data = parse("---\ntags: 'rust, git,'\n---\n", "a.md");
+        assert_eq!(list(&data, "tags"), ["rust", "git"]);
+        assert!(list(&data, "categories").is_empty());
+    }
+
+    #[test]
+    fn points() {
+        let data = |text: &str| parse(&format!("---\n{}\n---\n", text), "a.md");
+        assert_eq!(point(&data("lat: 52.52\nlon: 13")), Some((52.52, 13.0)));
+        assert_eq!(point(&data("location: {lat: '52.52', lon: 13.4}")), Some((52.52, 13.4)));
+        assert_eq!(point(&data("location: 52.52, 13.40")), Some((52.52, 13.4)));
+        assert_eq!(point(&data("location: 52.52 13.40")), Some((52.52, 13.4)));
+        assert_eq!(point(&data("location: 95, 13")), None);
+        assert_eq!(point(&data("location: Berlin")), None);
+    }
+}
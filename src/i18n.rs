@@ -0,0 +1,135 @@
+//! Localized titles of the items from Fluent files, see
+//! https://projectfluent.org/, and localized dates
+
+use fluent::{FluentBundle, FluentResource};
+use log::warn;
+use std::{borrow::Cow, cell::Cell, convert::TryFrom, error, fs};
+use unic_langid::LanguageIdentifier;
+use yaml_rust::Yaml;
+
+/// The built-in messages by language
+const BUILTIN: [(&str, &str); 2] = [
+    ("de", include_str!("i18n/de.ftl")),
+    ("en", include_str!("i18n/en.ftl")),
+];
+
+thread_local! {
+    /// the locale for the names of months and days in dates
+    static DATE_LOCALE: Cell<Option<chrono::Locale>> = const { Cell::new(None) };
+}
+
+/// Returns the locale for the dates of the placeholders
+pub fn date_locale() -> Option<chrono::Locale> {
+    DATE_LOCALE.with(Cell::get)
+}
+
+/// Returns the locale of the language tag for dates like `de_DE` for `de` or
+/// `de-DE`
+fn chrono_locale(lang: &LanguageIdentifier) -> Option<chrono::Locale> {
+    let language = lang.language.as_str();
+    let region = match (lang.region, language) {
+        (Some(region), _) => region.as_str().to_string(),
+        (None, "en") => "US".to_string(),
+        (None, _) => language.to_uppercase(),
+    };
+    chrono::Locale::try_from(format!("{}_{}", language, region).as_str()).ok()
+}
+
+/// The messages of the config entry `locale` and the file `locale-file`
+pub struct Strings {
+    bundle: Option<FluentBundle<FluentResource>>,
+}
+
+impl Strings {
+    pub fn from_conf(conf: &Yaml) -> Result<Self, Box<dyn error::Error>> {
+        let Some(locale) = conf["locale"].as_str() else {
+            DATE_LOCALE.with(|x| x.set(None));
+            return Ok(Strings { bundle: None });
+        };
+        let lang: LanguageIdentifier = locale.parse()
+            .map_err(|err| format!("Invalid value of config entry 'locale': {}", err))?;
+
+        let date_locale = chrono_locale(&lang);
+        if date_locale.is_none() {
+            warn!("No names of months and days for the locale {}; try it with region like de-AT", locale);
+        }
+        DATE_LOCALE.with(|x| x.set(date_locale));
+
+        let mut bundle = FluentBundle::new(vec![lang.clone()]);
+        // no Unicode isolation marks around placeables in titles
+        bundle.set_use_isolating(false);
+
+        let parse = |text: String, name: &str| FluentResource::try_new(text)
+            .map_err(|(_, errs)| format!("Invalid messages in {}: {:?}", name, errs));
+
+        if let Some((_, text)) = BUILTIN.iter().find(|(name, _)| *name == lang.language.as_str()) {
+            bundle.add_resource(parse(text.to_string(), "built-in messages")?)
+                .map_err(|errs| format!("{:?}", errs))?;
+        }
+
+        if let Some(path) = conf["locale-file"].as_str() {
+            let text = fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read locale-file {}: {}", path, err))?;
+            bundle.add_resource_overriding(parse(text, path)?);
+        } else if bundle.get_message("item-title-page-new").is_none() {
+            warn!("No built-in messages for the locale {}", locale);
+        }
+
+        Ok(Strings { bundle: Some(bundle) })
+    }
+
+    /// Returns the text of the config entry or else the localized message with
+    /// the key as id
+    pub fn text<'a>(&self, conf: &'a Yaml, key: &str) -> Option<Cow<'a, str>> {
+        if let Some(text) = conf[key].as_str() {
+            return Some(Cow::Borrowed(text));
+        }
+
+        let bundle = self.bundle.as_ref()?;
+        let pattern = bundle.get_message(key)?.value()?;
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, None, &mut errors);
+        for err in errors {
+            warn!("Error in message {}: {}", key, err);
+        }
+        Some(Cow::Owned(text.into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conf(text: &str) -> Yaml {
+        yaml_rust::YamlLoader::load_from_str(text).unwrap().remove(0)
+    }
+
+    #[test]
+    fn fallback() {
+        let conf_de = conf("{locale: de, item-title-page-removed: Weg /%p}");
+        let strings = Strings::from_conf(&conf_de).unwrap();
+        assert_eq!(strings.text(&conf_de, "item-title-page-new").as_deref(), Some("Neue Seite /%p"));
+        assert_eq!(strings.text(&conf_de, "item-title-page-removed").as_deref(), Some("Weg /%p"));
+        assert_eq!(strings.text(&conf_de, "item-title-file-new"), None);
+        assert_eq!(date_locale(), Some(chrono::Locale::de_DE));
+
+        let path = std::env::temp_dir().join("gitlog2rss-i18n-fallback.ftl");
+        fs::write(&path, "item-title-page-new = Neu: /%p\nitem-title-file-new = Datei /%p\n").unwrap();
+        let conf_file = conf(&format!("{{locale: en-GB, locale-file: '{}'}}", path.display()));
+        let strings = Strings::from_conf(&conf_file).unwrap();
+        assert_eq!(strings.text(&conf_file, "item-title-page-new").as_deref(), Some("Neu: /%p"));
+        assert_eq!(strings.text(&conf_file, "item-title-file-new").as_deref(), Some("Datei /%p"));
+        assert_eq!(strings.text(&conf_file, "item-title-page-removed").as_deref(), Some("Page /%p removed"));
+        assert_eq!(date_locale(), Some(chrono::Locale::en_GB));
+        fs::remove_file(path).unwrap();
+
+        let conf_none = conf("{item-title-page-new: Neu}");
+        let strings = Strings::from_conf(&conf_none).unwrap();
+        assert_eq!(strings.text(&conf_none, "item-title-page-new").as_deref(), Some("Neu"));
+        assert_eq!(strings.text(&conf_none, "item-title-page-removed"), None);
+        assert_eq!(date_locale(), None);
+
+        assert!(Strings::from_conf(&conf("{locale: '1 2'}")).is_err());
+        assert!(Strings::from_conf(&conf("{locale: de, locale-file: /nonexistent/de.ftl}")).is_err());
+    }
+}
@@ -0,0 +1,8 @@
+# The titles of the items with the placeholders of the config entries
+
+item-title-page-new = Neue Seite /%p
+item-title-page-removed = Seite /%p gelöscht
+item-title-page-modified = Seite /%p bearbeitet
+item-title-page-renamed = Seite /%p umbenannt
+item-title-batch = %n Seiten aktualisiert
+item-title-first-contribution = Erster Beitrag von %an
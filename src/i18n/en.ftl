@@ -0,0 +1,8 @@
+# The titles of the items with the placeholders of the config entries
+
+item-title-page-new = New page /%p
+item-title-page-removed = Page /%p removed
+item-title-page-modified = Page /%p modified
+item-title-page-renamed = Page /%p renamed
+item-title-batch = %n pages updated
+item-title-first-contribution = First contribution by %an
@@ -1,320 +1,4374 @@
+mod authors;
+mod content;
+mod diff;
+mod forge;
+mod frontmatter;
+mod i18n;
+mod markdown;
+mod output;
+mod plugin;
+mod podcast;
+mod refs;
+mod sanitize;
+mod server;
+mod summary;
+mod templates;
+mod xml;
+
 use chrono::{
+    DateTime,
     FixedOffset,
     TimeZone,
 };
-use clap::{Arg, ValueHint};
+use clap::{Arg, ArgAction, ValueHint};
 use git2::{
+    Blob,
     Delta,
+    DiffFile,
     DiffFindOptions,
     DiffOptions,
     Pathspec,
     PathspecFlags,
+    Oid,
     Repository,
 };
 use log::{
     debug,
+    error,
     info,
     trace,
     warn,
 };
 use rss::{
+    extension::{Extension, ExtensionMap},
+    Category,
     ChannelBuilder,
+    Enclosure,
+    Guid,
+    Image,
     ItemBuilder,
+    Source,
 };
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
     env,
     error,
+    ffi::OsStr,
     fs,
-    io::{self, Read},
+    io::{self, BufRead, Read},
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    process,
+    thread,
+    time::{Duration, Instant},
 };
 use yaml_rust::{
     Yaml,
     YamlLoader,
 };
 
-fn rfc822_time(time: &git2::Time) -> String {
+/// An item of the feed with the data it was created from
+#[derive(Clone)]
+pub struct Entry {
+    pub time: git2::Time,
+    pub commit: Oid,
+    pub status: Delta,
+    /// path of the file in the repository
+    pub path: String,
+    /// URL of the page of the file; `None` for items not about one file
+    pub page: Option<String>,
+    pub author_name: String,
+    pub author_email: String,
+    /// the homepage of the author from the config entry `authors`
+    pub author_uri: Option<String>,
+    pub item: rss::Item,
+}
+
+fn datetime(time: &git2::Time) -> DateTime<FixedOffset> {
     FixedOffset::east_opt(time.offset_minutes() * 60)
         .unwrap_or_else(|| panic!("Timestamp with invalid offset: {}", time.offset_minutes()))
         .timestamp_opt(time.seconds(), 0)
         .single()
         .unwrap_or_else(|| panic!("Timestamp with invalid seconds: {}", time.seconds()))
-        .to_rfc2822()
 }
 
-fn main() -> Result<(), Box<dyn error::Error + 'static>> {
-    let args = clap::Command::new(clap::crate_name!())
-        .version(clap::crate_version!())
-        .author(clap::crate_authors!(", "))
-        .about(clap::crate_description!())
-        .arg(
-            Arg::new("conf")
-                .short('c')
-                .long("conf")
-                .num_args(1)
-                .value_name("FILE")
-                .required(true)
-                .value_hint(ValueHint::FilePath)
-                .value_parser(clap::builder::NonEmptyStringValueParser::new())
-                .help("config file")
-        ).arg(
-            Arg::new("debug")
-                .short('d')
-                .long("debug")
-                .help("Print debug messages")
-        ).arg(
-            Arg::new("prefix")
-                .short('p')
-                .long("prefix")
-                .num_args(1)
-                .value_name("PREFIX")
-                .value_hint(ValueHint::Other)
-                .value_parser(clap::builder::NonEmptyStringValueParser::new())
-                .help("PREFIX gets removed from the beginning of file names")
-        ).arg(
-            Arg::new("pretty")
-                .short('y')
-                .long("pretty")
-                .help("Pretty print output")
-        ).arg(
-            Arg::new("path")
-                .value_name("PATH")
-                .help("Path of the source file")
-                .required(true)
-                .num_args(1..)
-                .value_hint(ValueHint::AnyPath)
-        ).get_matches();
-
-    {
-        let mut logger = env_logger::builder();
-        match env::var("RUST_LOG_TIMESTAMP").as_deref() {
-            Ok("sec") => { logger.format_timestamp_secs(); }
-            Ok("micro") => { logger.format_timestamp_micros(); }
-            Ok("milli") => { logger.format_timestamp_millis(); }
-            Ok("nano") => { logger.format_timestamp_nanos(); }
-            Ok(_) => { logger.format_timestamp(None); }
-            _ => {},
-        }
-
-        if args.contains_id("debug") {
-            logger.filter_level(log::LevelFilter::Trace);
-        }
+fn rfc822_time(time: &git2::Time) -> String {
+    datetime(time).to_rfc2822()
+}
 
-        logger.init();
+/// Returns the part of a pathspec before the first component with a wildcard
+/// or `None`, if the pathspec can't be reduced to a fixed path in the tree.
+fn pathspec_prefix(spec: &str) -> Option<&str> {
+    if spec.starts_with(':') {
+        // pathspec magic like :(exclude)
+        return None;
     }
 
-    let conf = {
-        let txt = match args.get_one::<String>("conf").unwrap().as_str() {
-            "-" => {
-                info!("Going to read config from stdin");
-                let mut buf = String::new();
-                io::stdin().read_to_string(&mut buf)?;
-                buf
-            }
-
-            path => {
-                info!("Going to read config file {}", path);
-                fs::read_to_string(path)?
-            }
-        };
-
-        YamlLoader::load_from_str(&txt)?.pop().unwrap()
-    };
+    // `.` and `./` name the top of the tree, which get_path() doesn't know
+    let spec = spec.trim_start_matches("./");
+    let prefix = match spec.find(['*', '?', '[', '\\']) {
+        Some(pos) => &spec[..spec[..pos].rfind('/')?],
+        None => spec,
+    }.trim_end_matches('/');
 
-    let mut diff_opts = DiffOptions::new();
-    diff_opts.ignore_filemode(true)
-        .ignore_submodules(true)
-        .ignore_whitespace(true);
+    if prefix.is_empty() || prefix == "." { None } else { Some(prefix) }
+}
 
-    for e in args.get_many::<String>("path").unwrap() {
-        info!("using path filter {}", e);
-        diff_opts.pathspec(e);
+/// Returns the name and version of this program and the commit it was built
+/// from, if known
+fn default_generator() -> String {
+    let mut name = format!("{} {}", clap::crate_name!(), clap::crate_version!());
+    if let Some(commit) = option_env!("GITLOG2RSS_BUILD_COMMIT") {
+        name = name + " (" + commit + ")";
     }
+    name
+}
 
-    let mut diff_similar_opts = DiffFindOptions::default();
-    diff_similar_opts.renames(true);
-
-    let ignored_files = if let Some(list) = conf["ignore-files"].as_vec() {
-        Some(Pathspec::new(list.iter().filter_map(|x| x.as_str()))?)
-    } else {
-        None
-    };
+/// Finds items with the same GUID, or link for items without GUID, which
+/// readers would show as one item; `mode` is one of `warn` (only about items
+/// with the same GUID, because the changes of a page share its link), `suffix`
+/// (appends `#2`, `#3`, … to the later ones) or `merge` (keeps only the newest
+/// one). The items must be sorted by date.
+fn resolve_duplicates(items: &mut Vec<Entry>, mode: &str) -> Result<(), Box<dyn error::Error>> {
+    use std::collections::{HashMap, HashSet};
 
-    let repo = if let Some(path) = conf["repo"].as_str() {
-        info!("Opening git repository {}", path);
-        Repository::open(path)?
-    } else {
-        let repo = Repository::open_from_env()?;
-        info!("Successfully opened git repository {}", repo.path().display());
-        repo
+    let by_link = mode != "warn";
+    let key = |entry: &Entry| -> Option<String> {
+        let guid = entry.item.guid().map(|x| x.value());
+        if by_link { guid.or_else(|| entry.item.link()) } else { guid }.map(str::to_string)
     };
 
-    let base_url = url::Url::parse(conf["base-url"].as_str().unwrap())?;
-    let strip_prefix = args.get_one("prefix")
-        .copied()
-        .or_else(|| conf["strip-prefix"].as_str())
-        .unwrap_or("");
-
-    let mut items = Vec::new();
-
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
-    for id in revwalk {
-        let commit = repo.find_commit(id?)?;
-        if commit.parent_count() > 1 {
-            debug!("Skipping merge commit {}", commit.id());
-            continue;
-        }
-        if commit.message().map_or(false, |msg| msg.contains("\nno-rss\n")) {
-            info!("Skipping commit {}, because of \"no-rss\"", commit.id());
-            continue;
+    let mut counts = HashMap::<String, usize>::new();
+    for item in items.iter() {
+        if let Some(key) = key(item) {
+            *counts.entry(key).or_default() += 1;
         }
+    }
+    counts.retain(|_, count| *count > 1);
 
-        let author = commit.author();
-        let author_date = rfc822_time(&author.when());
-        let author = author.email().unwrap().to_string()
-            + " (" + author.name().unwrap() + ")";
-
-        let parent_tree = if commit.parent_count() == 1 {
-            Some(commit.parent(0)?.tree()?)
-        } else {
-            None
-        };
-
-        let diff = repo.diff_tree_to_tree(
-            parent_tree.as_ref(), Some(&commit.tree()?), Some(&mut diff_opts)
-        )?;
-        // to find renames or copies
-        // diff.find_similar(Some(&mut diff_similar_opts))?;
-
-        for delta in diff.deltas() {
-            trace!("{} {:?} {:?}, {:?}",
-                   commit.id(),
-                   delta.status(),
-                   delta.old_file().path(),
-                   delta.new_file().path(),
-            );
-
-            let file;
-            let text;
-            match delta.status() {
-                Delta::Added => {
-                    file = delta.new_file();
-                    text = "item-title-page-new";
-                }
-
-                Delta::Deleted => {
-                    file = delta.old_file();
-                    text = "item-title-page-removed";
-                }
+    match mode {
+        "warn" => {
+            for (key, count) in &counts {
+                warn!("{} items share the GUID {}", count, key);
+            }
+        }
 
-                Delta::Modified => {
-                    file = delta.new_file();
-                    text = "item-title-page-modified"
-                }
+        "suffix" => {
+            let mut seen = HashMap::<String, usize>::new();
+            for item in items.iter_mut() {
+                let key = match key(item) {
+                    Some(key) if counts.contains_key(&key) => key,
+                    _ => continue,
+                };
 
-                st => {
-                    warn!(
-                        "Unhandled diff state {:?} for commit {} between {:?} and {:?}",
-                        st,
-                        commit.id(),
-                        delta.old_file().path(),
-                        delta.new_file().path(),
-                    );
+                let num = seen.entry(key.clone()).or_default();
+                *num += 1;
+                if *num == 1 {
                     continue;
                 }
-            }
-
-            let path = file.path().unwrap();
 
-            if let Some(ref ign) = ignored_files {
-                if ign.matches_path(path, PathspecFlags::default()) {
-                    info!("Skipping delta of ignored file {} in commit {}",
-                          path.display(), commit.id());
-                    continue;
+                let value = format!("{}#{}", key, num);
+                debug!("Renaming duplicate {} to {}", key, value);
+                match item.item.guid.as_mut() {
+                    Some(guid) => guid.value = value,
+                    None => item.item.link = Some(value),
                 }
             }
+        }
 
-            let path = path.to_str().unwrap();
-            let url_path = {
-                let first = if path.starts_with(strip_prefix) { strip_prefix.len() } else { 0 };
-
-                if path.ends_with(".md") {
-                    path[first..path.len() - 2].to_string() + "html"
-                } else {
-                    path[first..].to_string()
+        "merge" => {
+            let mut seen = HashSet::new();
+            // keep the last, i.e. newest, item of each key
+            items.reverse();
+            items.retain(|item| match key(item) {
+                Some(key) if counts.contains_key(&key) => {
+                    let first = seen.insert(key.clone());
+                    if !first {
+                        debug!("Dropping older item with duplicate {}", key);
+                    }
+                    first
                 }
-            };
-
-            items.push(
-                (
-                    commit.author().when(),
-                    ItemBuilder::default()
-                        .author(Some(author.clone()))
-                    // TODO .description(Some("Neue Seite erstellt".into()));
-                    // TODO .categories(vec![])
-                    // TODO .guid(Some(Guid))
-                        .pub_date(Some(author_date.clone()))
-                        .title(
-                            conf[text].as_str().map(|title| title.replace("%p", &url_path))
-                        )
-                        .link(Some(base_url.join(&url_path)?.into()))
-                        .build()
-                )
-            );
-            debug!("New rss item for {}:{}", commit.id(), path)
+                _ => true,
+            });
+            items.reverse();
         }
+
+        x => return Err(format!("Invalid value of config entry 'duplicate-items': {}", x).into()),
     }
 
-    items.sort_unstable_by_key(|e| e.0);
-    let items = items.into_iter().map(|e| e.1).collect::<Vec<_>>();
+    Ok(())
+}
+
+/// Builds the RSS channel from the config and the items
+fn build_channel(conf: &Yaml, items: &[Entry]) -> Result<rss::Channel, Box<dyn error::Error>> {
+    let mut namespaces = BTreeMap::new();
+    if items.iter().any(|x| x.item.extensions().contains_key("media")) {
+        namespaces.insert("media".to_string(), "http://search.yahoo.com/mrss/".to_string());
+    }
+    if items.iter().any(|x| x.item.extensions().contains_key("georss")) {
+        namespaces.insert("georss".to_string(), "http://www.georss.org/georss".to_string());
+    }
 
-    let chan = ChannelBuilder::default()
+    let mut chan = ChannelBuilder::default()
         .title(conf["channel-title"].as_str().unwrap())
         .link(conf["channel-link"].as_str().unwrap())
         .description(conf["channel-description"].as_str().unwrap())
-        .pub_date(items.first().and_then(|x| x.pub_date()).map(|x| x.to_owned()))
-        .last_build_date(items.last().and_then(|x| x.pub_date()).map(|x| x.to_owned()))
+        .pub_date(items.first().and_then(|x| x.item.pub_date()).map(|x| x.to_owned()))
+        .last_build_date(match conf["last-build-date"].as_str() {
+            // the items are sorted by date, the newest is the last one
+            None | Some("newest-item") => items.last().and_then(|x| x.item.pub_date()).map(|x| x.to_owned()),
+            Some("now") => Some(chrono::Local::now().to_rfc2822()),
+            Some("none") => None,
+            Some(x) => return Err(format!("Invalid value of config entry 'last-build-date': {}", x).into()),
+        })
         .language(conf["language"].as_str().map(|x| x.to_owned()))
         .copyright(conf["copyright"].as_str().map(|x| x.to_owned()))
         .managing_editor(conf["managing-editor"].as_str().map(|x| x.to_owned()))
         .webmaster(conf["webmaster"].as_str().map(|x| x.to_owned()))
-    // TODO .categories(vec![])
-        .generator(conf["generator"].as_str().map(|x| x.to_owned()))
+        .image(match &conf["channel-image"] {
+            Yaml::BadValue => None,
+            img @ Yaml::Hash(_) => {
+                let size = |key: &str| match &img[key] {
+                    Yaml::Integer(x) if *x > 0 => Ok(Some(x.to_string())),
+                    Yaml::BadValue => Ok(None),
+                    _ => Err(format!("Invalid value of config entry 'channel-image.{}'", key)),
+                };
+                Some(Image {
+                    url: img["url"].as_str().ok_or("Missing config entry 'channel-image.url'")?.to_string(),
+                    title: img["title"].as_str().or_else(|| conf["channel-title"].as_str()).unwrap().to_string(),
+                    link: img["link"].as_str().or_else(|| conf["channel-link"].as_str()).unwrap().to_string(),
+                    width: size("width")?,
+                    height: size("height")?,
+                    description: img["description"].as_str().map(str::to_string),
+                })
+            }
+            _ => return Err("Invalid value of config entry 'channel-image'".into()),
+        })
+        .categories(match &conf["channel-categories"] {
+            Yaml::Array(list) => list.iter()
+                .map(|x| conf_category(x, "channel-categories"))
+                .collect::<Result<Vec<_>, _>>()?,
+            Yaml::BadValue => Vec::new(),
+            _ => return Err("Invalid value of config entry 'channel-categories': expected a list".into()),
+        })
+        .generator(match &conf["generator"] {
+            Yaml::String(x) => Some(expand(x, &[
+                ("n", clap::crate_name!().to_string()),
+                ("v", clap::crate_version!().to_string()),
+            ])),
+            Yaml::BadValue => Some(default_generator()),
+            Yaml::Null | Yaml::Boolean(false) => None,
+            _ => return Err("Invalid value of config entry 'generator'".into())
+        })
         .ttl(match &conf["ttl"] {
+            Yaml::String(x) if x == "auto" => Some(format!("{}", auto_ttl(items))),
             Yaml::Integer(x) => Some(format!("{}", x)),
             Yaml::String(x) => Some(format!("{}", humantime::parse_duration(x)?.as_secs() / 60)),
             Yaml::BadValue => None,
             _ => return Err("Invalid value of config entry 'ttl'".into())
         })
-        .skip_hours(
-            conf["skip-hours"].as_vec()
-                .map_or(
-                    vec![],
-                    |vec| vec.iter()
-                        .filter_map(|x| x.as_i64())
-                        .map(|x| format!("{}", x))
-                        .collect()
-                )
-        )
-        .skip_days(
-            conf["skip-days"].as_vec()
-                .map_or(
-                    vec![],
-                    |vec| vec.iter()
-                        .filter_map(|x| x.as_i64())
-                        .map(|x| format!("{}", x))
-                        .collect()
-                )
-        )
-        .items(items)
+        .skip_hours(skip_hours(&conf["skip-hours"])?)
+        .skip_days(skip_days(&conf["skip-days"])?)
+        .items(items.iter().map(|x| x.item.clone()).collect::<Vec<_>>())
+        .namespaces(namespaces)
         .build();
+    chan.itunes_ext = podcast::channel(&conf["podcast"])?;
+
+    let hubs = match &conf["websub-hub"] {
+        Yaml::String(x) => vec![x.as_str()],
+        Yaml::Array(list) => list.iter()
+            .map(|x| x.as_str().ok_or("Invalid value in config entry 'websub-hub'"))
+            .collect::<Result<_, _>>()?,
+        Yaml::BadValue => Vec::new(),
+        _ => return Err("Invalid value of config entry 'websub-hub'".into()),
+    };
+    if !hubs.is_empty() {
+        chan.atom_ext.get_or_insert_with(Default::default).links.extend(hubs.iter().map(|hub| {
+            rss::extension::atom::Link { href: hub.to_string(), rel: "hub".into(), ..Default::default() }
+        }));
+    }
+
+    Ok(chan)
+}
+
+/// Returns the TTL in minutes from the median interval between the recent
+/// changes, clamped to one hour up to one week
+fn auto_ttl(items: &[Entry]) -> i64 {
+    const MIN: i64 = 60;
+    const MAX: i64 = 7 * 24 * 60;
+
+    // the items are sorted by date
+    let mut times: Vec<_> = items.iter().rev().take(21).map(|x| x.time.seconds()).collect();
+    times.dedup();
+    let mut intervals: Vec<_> = times.windows(2).map(|x| (x[0] - x[1]) / 60).collect();
+    if intervals.is_empty() {
+        return MAX;
+    }
+
+    intervals.sort_unstable();
+    intervals[intervals.len() / 2].clamp(MIN, MAX)
+}
+
+/// Splits the items into groups with one feed each; the key of a group is the
+/// first directory of the path (`section`) or the name of the author
+/// (`author`), to be used as placeholder in output paths
+fn split_entries(entries: Vec<Entry>, split_by: Option<&str>, strip_prefix: &str)
+                 -> BTreeMap<Option<String>, Vec<Entry>>
+{
+    let mut groups = BTreeMap::<_, Vec<_>>::new();
+    if split_by.is_none() {
+        // without items, too, the outputs get an empty channel
+        groups.insert(None, Vec::new());
+    }
+
+    for entry in entries {
+        let key = match split_by {
+            Some("section") => Some(section(&entry.path, strip_prefix).to_string()),
+            Some(_) => Some(slug(&entry.author_name)),
+            None => None,
+        };
+        groups.entry(key).or_default().push(entry);
+    }
+
+    groups
+}
+
+/// Returns the refs of the config entry `refs` by name and commit, HEAD without
+/// the entry; globs like refs/heads/release-* give all matching refs
+fn walked_refs(conf: &Yaml, repo: &Repository) -> Result<Vec<(String, Oid)>, Box<dyn error::Error>> {
+    let mut refs = Vec::new();
+    match conf {
+        Yaml::Array(list) => for name in list {
+            let name = name.as_str().ok_or("Invalid value in config entry 'refs'")?;
+            if name.contains(['*', '?', '[']) {
+                for reference in repo.references_glob(name)? {
+                    let reference = reference?;
+                    let short = reference.shorthand().unwrap_or(name).to_string();
+                    match reference.peel_to_commit() {
+                        Ok(commit) => refs.push((short, commit.id())),
+                        // e.g. a tag of a blob
+                        Err(err) => warn!("Skipping ref {}, because it points to no commit: {}",
+                                          short, err.message()),
+                    }
+                }
+            } else {
+                let id = repo.revparse_single(name)?.peel_to_commit()?.id();
+                refs.push((name.to_string(), id));
+            }
+        }
+        Yaml::BadValue => refs.push(("HEAD".to_string(), repo.head()?.peel_to_commit()?.id())),
+        _ => return Err("Invalid value of config entry 'refs'".into()),
+    }
+    Ok(refs)
+}
+
+/// Builds one item for the entries of a commit, which lists the entries in its
+/// description
+fn batch_entry(entries: Vec<Entry>, title: &str, base_url: &url::Url, with_guid: bool)
+               -> Result<Entry, Box<dyn error::Error>>
+{
+    let mut first = entries[0].clone();
+    let list = entries.iter()
+        .map(|e| {
+            let title = html_escape(e.item.title().unwrap_or(&e.path));
+            match e.item.link() {
+                Some(link) => format!("<li><a href=\"{}\">{}</a></li>", html_escape(link), title),
+                None => format!("<li>{}</li>", title),
+            }
+        })
+        .collect::<String>();
 
-    if args.contains_id("pretty") {
-        chan.pretty_write_to(&mut io::stdout(), b' ', 2)?;
-        println!();
+    let guid = if with_guid {
+        let id = Oid::hash_object(git2::ObjectType::Blob, format!("{}:", first.commit).as_bytes())?;
+        Some(Guid { value: id.to_string(), permalink: false })
     } else {
-        chan.write_to(&mut io::stdout())?;
+        None
+    };
+
+    first.status = Delta::Modified;
+    first.page = None;
+    first.item = ItemBuilder::default()
+        .author(first.item.author().map(String::from))
+        .pub_date(first.item.pub_date().map(String::from))
+        .title(Some(expand(title, &[("n", entries.len().to_string())])))
+        .link(Some(base_url.to_string()))
+        .description(Some(format!("<ul>{}</ul>", list)))
+        .guid(guid)
+        .build();
+    Ok(first)
+}
+
+/// Returns the commit from which the commit with the message was cherry-picked,
+/// as noted by `git cherry-pick -x` or the trailer `x-original-commit`
+fn original_commit(msg: &str) -> Option<&str> {
+    msg.lines().rev().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("(cherry picked from commit ")
+            .and_then(|x| x.strip_suffix(')'))
+            .or_else(|| {
+                line.split_once(':')
+                    .filter(|(key, _)| key.trim().eq_ignore_ascii_case("x-original-commit"))
+                    .map(|(_, value)| value.trim())
+            })
+            .filter(|id| !id.is_empty() && id.bytes().all(|c| c.is_ascii_hexdigit()))
+    })
+}
+
+/// Returns the stdout of the shell command for the description of the item
+/// or `None`, if it's empty, the command fails or runs longer than `timeout`;
+/// it runs in the directory `dir`
+fn command_description(command: &str, dir: &Path, env: &[(&str, String)], timeout: Duration)
+                       -> Result<Option<String>, Box<dyn error::Error>>
+{
+    let mut child = process::Command::new("sh")
+        .args(["-c", command])
+        .current_dir(dir)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to run description-command: {}", err))?;
+
+    // read in threads, because a full pipe blocks the command
+    let read = |pipe: Option<Box<dyn Read + Send>>| thread::spawn(move || {
+        let mut data = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut data);
+        }
+        data
+    });
+    let stdout = read(child.stdout.take().map(|x| Box::new(x) as _));
+    let stderr = read(child.stderr.take().map(|x| Box::new(x) as _));
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            warn!("description-command timed out after {} for {:?}", humantime::format_duration(timeout), env);
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        warn!("description-command failed ({}) for {:?}: {}", status, env,
+              String::from_utf8_lossy(&stderr).trim());
+        return Ok(None);
     }
 
-    Ok(())
+    let text = String::from_utf8_lossy(&stdout).trim().to_string();
+    Ok(Some(text).filter(|x| !x.is_empty()))
+}
+
+/// Returns the subject and body of the commit message as HTML paragraphs,
+/// without the trailers like `Signed-off-by:` and the note of `git
+/// cherry-pick -x`
+fn message_html(msg: &str) -> String {
+    let is_trailer = |line: &str| {
+        line.starts_with("(cherry picked from commit ")
+            || line.split_once(": ").is_some_and(|(key, _)| {
+                !key.is_empty() && key.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-')
+            })
+    };
+
+    let mut paragraphs: Vec<_> = msg.split("\n\n")
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .collect();
+    // the subject is never a trailer
+    while paragraphs.len() > 1 && paragraphs.last().unwrap().lines().all(|x| is_trailer(x.trim())) {
+        paragraphs.pop();
+    }
+
+    paragraphs.iter()
+        .map(|x| format!("<p>{}</p>", html_escape(x)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// An update of a ref pushed to the repository
+struct RefUpdate {
+    name: String,
+    old: Oid,
+    new: Oid,
+}
+
+/// Reads the updated refs in the format of a post-receive hook (`<old> <new>
+/// <ref>` per line) from `input` and returns the updates of the refs matching
+/// the patterns `refs` or, without them, of the ref `head`; deleted refs are
+/// ignored
+fn hook_updates(input: impl BufRead, refs: &Yaml, head: &str) -> Result<Vec<RefUpdate>, Box<dyn error::Error>> {
+    let patterns = match refs {
+        Yaml::Array(list) => list.iter().filter_map(|x| x.as_str()).collect(),
+        _ => vec![head],
+    };
+    let pathspec = Pathspec::new(patterns.iter())?;
+
+    let mut updates = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        let mut fields = line.split_whitespace();
+        let (Some(old), Some(new), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(format!("Invalid line from post-receive: {}", line).into());
+        };
+        let (old, new) = (Oid::from_str(old)?, Oid::from_str(new)?);
+
+        let short = name.strip_prefix("refs/heads/")
+            .or_else(|| name.strip_prefix("refs/tags/"))
+            .unwrap_or(name);
+        let matches = [name, short].iter()
+            .any(|x| pathspec.matches_path(Path::new(x), PathspecFlags::default()));
+        if !matches || new.is_zero() {
+            debug!("Ignoring update of {}", name);
+            continue;
+        }
+
+        updates.push(RefUpdate { name: short.to_string(), old, new });
+    }
+
+    Ok(updates)
+}
+
+/// Returns the first directory of the path after the prefix or `root`
+fn section<'a>(path: &'a str, strip_prefix: &str) -> &'a str {
+    let path = path.strip_prefix(strip_prefix).unwrap_or(path);
+    path.split_once('/').map_or("root", |(dir, _)| dir)
+}
+
+/// Reads a category from the config, which is the name or a map with name
+/// and domain
+fn conf_category(conf: &Yaml, key: &str) -> Result<Category, Box<dyn error::Error>> {
+    match conf {
+        Yaml::String(name) => Ok(Category { name: name.clone(), domain: None }),
+        Yaml::Hash(_) => Ok(Category {
+            name: conf["name"].as_str()
+                .ok_or_else(|| format!("Missing name of category in config entry '{}'", key))?
+                .to_string(),
+            domain: conf["domain"].as_str().map(str::to_string),
+        }),
+        _ => Err(format!("Invalid category in config entry '{}'", key).into()),
+    }
+}
+
+/// Returns the first `depth` directories of the path after `strip_prefix`, or
+/// `None` for files in the top directory
+fn path_category<'a>(path: &'a str, strip_prefix: &str, depth: usize) -> Option<&'a str> {
+    let path = path.strip_prefix(strip_prefix).unwrap_or(path);
+    let dir = &path[..path.rfind('/')?];
+    match dir.match_indices('/').nth(depth.checked_sub(1)?) {
+        Some((pos, _)) => Some(&dir[..pos]),
+        None => Some(dir),
+    }
+}
+
+/// Converts the text to lower case letters, digits and hyphens
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Replaces the author with email of the items by `dc:creator` with the name
+/// only; the email is removed from the other formats, too
+fn use_dc_creator(items: &mut [Entry]) {
+    for e in items {
+        e.item.set_author(None);
+        e.author_email.clear();
+        if !e.author_name.is_empty() {
+            e.item.set_dublin_core_ext(Some(rss::extension::dublincore::DublinCoreExtension {
+                creators: vec![e.author_name.clone()],
+                ..Default::default()
+            }));
+        }
+    }
+}
+
+/// Checks the requirements of RSS 2.0 for the channel and the items
+fn check_strict(conf: &Yaml, items: &[Entry], base_url: &url::Url)
+                -> Result<(), Box<dyn error::Error>>
+{
+    let mut problems = Vec::new();
+
+    for key in ["channel-title", "channel-link", "channel-description"] {
+        if conf[key].as_str().is_none_or(str::is_empty) {
+            problems.push(format!("Required config entry '{}' is missing", key));
+        }
+    }
+
+    for (key, max) in [("width", 144), ("height", 400)] {
+        if conf["channel-image"][key].as_i64().is_some_and(|x| x > max) {
+            problems.push(format!("The {} of channel-image is larger than {}", key, max));
+        }
+    }
+
+    // links to the forge may point anywhere, but must be absolute
+    let check_url = |problems: &mut Vec<String>, what: &str, value: &str| {
+        if let Err(err) = url::Url::parse(value) {
+            problems.push(format!("{} {} is no valid URL: {}", what, value, err));
+        }
+    };
+    let check_site_url = |problems: &mut Vec<String>, what: &str, value: &str| {
+        match url::Url::parse(value) {
+            Ok(x) if x.origin() == base_url.origin() && x.path().starts_with(base_url.path()) => {}
+            Ok(_) => problems.push(format!("{} {} is outside of base-url {}", what, value, base_url)),
+            Err(err) => problems.push(format!("{} {} is no valid URL: {}", what, value, err)),
+        }
+    };
+
+    if let Some(link) = conf["channel-link"].as_str().filter(|x| !x.is_empty()) {
+        check_site_url(&mut problems, "Channel link", link);
+    }
+
+    for e in items {
+        let item = &e.item;
+        let name = item.link().or_else(|| item.title()).unwrap_or("<unknown>");
+
+        if item.title().is_none() && item.description().is_none() {
+            problems.push(format!("Item {} has neither title nor description", name));
+        }
+
+        if let Some(date) = item.pub_date() {
+            if let Err(err) = chrono::DateTime::parse_from_rfc2822(date) {
+                problems.push(format!("Item {} has an invalid date {}: {}", name, date, err));
+            }
+        }
+
+        if let Some(link) = item.link() {
+            check_url(&mut problems, "Link", link);
+        }
+        if let Some(ref page) = e.page {
+            check_site_url(&mut problems, "Page", page);
+        }
+
+        if let Some(enclosure) = item.enclosure() {
+            check_site_url(&mut problems, "Enclosure", enclosure.url());
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    for problem in &problems {
+        error!("{}", problem);
+    }
+
+    Err(format!("Feed violates RSS 2.0 in {} places", problems.len()).into())
+}
+
+/// Returns the template for the texts of the root index, whose path `/`
+/// replaces the slash before `%p`
+fn root_template(template: &str) -> String {
+    template.replace("/%p", "%p")
+}
+
+/// Replaces the placeholders `%name` in the template; for overlapping names
+/// the longest one wins and `%%` gives a single `%`; `%{name:format}` formats
+/// the RFC 3339 date of the variable with the strftime format
+fn expand(template: &str, vars: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(pos) = rest.find('%') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        if let Some(tail) = rest.strip_prefix('%') {
+            out.push('%');
+            rest = tail;
+            continue;
+        }
+
+        if let Some((spec, tail)) = rest.strip_prefix('{').and_then(|x| x.split_once('}')) {
+            let date = spec.split_once(':').and_then(|(name, format)| {
+                let (_, value) = vars.iter().find(|(x, _)| *x == name)?;
+                let date = DateTime::parse_from_rfc3339(value).ok()?;
+                let items = chrono::format::StrftimeItems::new(format).parse().ok()?;
+                Some(match i18n::date_locale() {
+                    Some(locale) => date.format_localized_with_items(items.iter(), locale).to_string(),
+                    None => date.format_with_items(items.iter()).to_string(),
+                })
+            });
+            if let Some(date) = date {
+                out.push_str(&date);
+                rest = tail;
+                continue;
+            }
+        }
+
+        match vars.iter().filter(|(name, _)| rest.starts_with(name)).max_by_key(|(name, _)| name.len()) {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &rest[name.len()..];
+            }
+            None => out.push('%'),
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Escapes the characters with special meaning in HTML
+fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Truncates the text of the HTML after `max` graphemes with an ellipsis and
+/// closes the open elements
+fn truncate_html(html: &str, max: usize) -> String {
+    use unicode_segmentation::UnicodeSegmentation;
+    const VOID: [&str; 6] = ["br", "hr", "img", "input", "meta", "wbr"];
+
+    let mut out = String::with_capacity(html.len().min(max * 4));
+    let mut open = Vec::new();
+    let mut count = 0;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let (token, is_text) = if rest.starts_with('<') {
+            (&rest[..rest.find('>').map_or(rest.len(), |x| x + 1)], false)
+        } else if rest.starts_with('&') {
+            // an entity counts as one grapheme
+            (&rest[..rest.find(';').filter(|x| *x < 10).map_or(1, |x| x + 1)], true)
+        } else {
+            let end = rest.find(['<', '&']).unwrap_or(rest.len());
+            (rest[..end].graphemes(true).next().unwrap(), true)
+        };
+        rest = &rest[token.len()..];
+
+        if is_text {
+            if count == max {
+                out.push('…');
+                break;
+            }
+            count += 1;
+        } else if let Some(name) = token.strip_prefix("</") {
+            let name = name.trim_end_matches('>').trim();
+            if let Some(pos) = open.iter().rposition(|x| *x == name) {
+                open.truncate(pos);
+            }
+        } else if !token.starts_with("<!") && !token.ends_with("/>") {
+            let name = token[1..].split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or_default();
+            if !name.is_empty() && !VOID.contains(&name.to_ascii_lowercase().as_str()) {
+                open.push(name);
+            }
+        }
+        out.push_str(token);
+    }
+
+    for name in open.iter().rev() {
+        out.push_str("</");
+        out.push_str(name);
+        out.push('>');
+    }
+    out
+}
+
+/// Formats a scalar config value for error messages
+fn yaml_display(value: &Yaml) -> String {
+    match value {
+        Yaml::Real(x) | Yaml::String(x) => format!("'{}'", x),
+        Yaml::Integer(x) => x.to_string(),
+        Yaml::Boolean(x) => x.to_string(),
+        Yaml::Null => "null".into(),
+        x => format!("{:?}", x),
+    }
+}
+
+/// Reads the list `skip-hours` with up to 24 distinct hours from 0 to 23
+fn skip_hours(conf: &Yaml) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let list = match conf {
+        Yaml::Array(list) => list,
+        Yaml::BadValue => return Ok(vec![]),
+        _ => return Err("Invalid value of config entry 'skip-hours': expected a list".into()),
+    };
+
+    let mut hours = Vec::new();
+    for entry in list {
+        match entry.as_i64() {
+            Some(x @ 0..=23) if !hours.contains(&x) => hours.push(x),
+            Some(x @ 0..=23) => return Err(format!("Duplicate hour {} in config entry 'skip-hours'", x).into()),
+            _ => return Err(format!("Invalid hour {} in config entry 'skip-hours': \
+                                     must be a number from 0 to 23", yaml_display(entry)).into()),
+        }
+    }
+
+    Ok(hours.into_iter().map(|x| x.to_string()).collect())
+}
+
+/// Reads the list `skip-days` with distinct names of week days
+fn skip_days(conf: &Yaml) -> Result<Vec<String>, Box<dyn error::Error>> {
+    const DAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+    let list = match conf {
+        Yaml::Array(list) => list,
+        Yaml::BadValue => return Ok(vec![]),
+        _ => return Err("Invalid value of config entry 'skip-days': expected a list".into()),
+    };
+
+    let mut days = Vec::new();
+    for entry in list {
+        let day = entry.as_str()
+            .and_then(|x| DAYS.iter().find(|day| day.eq_ignore_ascii_case(x)))
+            .ok_or_else(|| format!("Invalid day {} in config entry 'skip-days': \
+                                    must be one of {}", yaml_display(entry), DAYS.join(", ")))?;
+        if days.contains(day) {
+            return Err(format!("Duplicate day {} in config entry 'skip-days'", day).into());
+        }
+        days.push(day);
+    }
+
+    Ok(days.into_iter().map(|x| x.to_string()).collect())
+}
+
+/// Reads a size in bytes from the config; strings can have one of the
+/// suffixes `k`, `M` or `G`
+fn conf_size(conf: &Yaml, key: &str) -> Result<Option<usize>, Box<dyn error::Error>> {
+    match conf {
+        Yaml::Integer(x) if *x >= 0 => Ok(Some(*x as usize)),
+        Yaml::String(x) => {
+            let (num, factor) = match x.trim().char_indices().last() {
+                Some((pos, 'k' | 'K')) => (&x[..pos], 1 << 10),
+                Some((pos, 'M')) => (&x[..pos], 1 << 20),
+                Some((pos, 'G')) => (&x[..pos], 1 << 30),
+                _ => (x.as_str(), 1),
+            };
+
+            num.trim().parse::<usize>()
+                .map(|x| Some(x * factor))
+                .map_err(|_| format!("Invalid size for config entry '{}': {}", key, x).into())
+        }
+        Yaml::BadValue => Ok(None),
+        _ => Err(format!("Invalid value of config entry '{}'", key).into()),
+    }
+}
+
+/// Applies the settings of the config block `odb` to libgit2; this must
+/// happen before the repository gets opened
+fn tune_odb(conf: &Yaml) -> Result<(), Box<dyn error::Error>> {
+    use libgit2_sys as raw;
+    use std::os::raw::c_int;
+
+    // git2 initializes libgit2 lazily, but git_libgit2_opts needs it set up
+    raw::init();
+
+    let check = |ret: c_int, key: &str| -> Result<(), Box<dyn error::Error>> {
+        match git2::Error::last_error(ret) {
+            Some(err) if ret < 0 => Err(format!("Failed to set libgit2 option {}: {}", key, err).into()),
+            _ => Ok(()),
+        }
+    };
+
+    if let Some(enabled) = conf["caching"].as_bool() {
+        debug!("Setting object caching to {}", enabled);
+        git2::opts::enable_caching(enabled);
+    }
+
+    for (key, opt) in [
+        ("mwindow-size", raw::GIT_OPT_SET_MWINDOW_SIZE),
+        ("mwindow-mapped-limit", raw::GIT_OPT_SET_MWINDOW_MAPPED_LIMIT),
+        ("mwindow-file-limit", raw::GIT_OPT_SET_MWINDOW_FILE_LIMIT),
+    ] {
+        if let Some(value) = conf_size(&conf[key], key)? {
+            debug!("Setting libgit2 option {} to {}", key, value);
+            check(unsafe { raw::git_libgit2_opts(opt as c_int, value) }, key)?;
+        }
+    }
+
+    if let Some(value) = conf_size(&conf["cache-max-size"], "cache-max-size")? {
+        debug!("Setting libgit2 option cache-max-size to {}", value);
+        let ret = unsafe {
+            raw::git_libgit2_opts(raw::GIT_OPT_SET_CACHE_MAX_SIZE as c_int, value as isize)
+        };
+        check(ret, "cache-max-size")?;
+    }
+
+    for (key, kind) in [
+        ("commit", raw::GIT_OBJECT_COMMIT),
+        ("tree", raw::GIT_OBJECT_TREE),
+        ("blob", raw::GIT_OBJECT_BLOB),
+    ] {
+        let conf_key = format!("cache-object-limit.{}", key);
+        if let Some(value) = conf_size(&conf["cache-object-limit"][key], &conf_key)? {
+            debug!("Setting libgit2 option {} to {}", conf_key, value);
+            let ret = unsafe {
+                raw::git_libgit2_opts(raw::GIT_OPT_SET_CACHE_OBJECT_LIMIT as c_int, kind, value)
+            };
+            check(ret, &conf_key)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads a blob, if it isn't larger than `max_size`
+fn load_blob(repo: &Repository, id: Oid, max_size: Option<usize>)
+             -> Result<Option<Blob<'_>>, git2::Error>
+{
+    if let Some(max) = max_size {
+        let (size, _) = repo.odb()?.read_header(id)?;
+        if size > max {
+            warn!("Skipping blob {} with {} bytes, because it is larger than {} bytes",
+                  id, size, max);
+            return Ok(None);
+        }
+    }
+
+    repo.find_blob(id).map(Some)
+}
+
+/// Checks if the file of a delta contains binary data; files larger than
+/// `max_size` are treated as binary, like libgit2 does
+fn is_binary(repo: &Repository, file: &DiffFile, max_size: Option<usize>)
+             -> Result<bool, git2::Error>
+{
+    if file.is_binary() {
+        return Ok(true);
+    }
+    if file.is_not_binary() {
+        return Ok(false);
+    }
+
+    Ok(load_blob(repo, file.id(), max_size)?.is_none_or(|blob| blob.is_binary()))
+}
+
+/// Returns the path with the extension of the last component replaced by the
+/// one of the map, e.g. `.html` for `.md`
+fn map_extension(path: &str, map: &HashMap<String, String>) -> String {
+    let start = path.rfind('/').map_or(0, |x| x + 1);
+    match path[start..].rfind('.').filter(|dot| *dot > 0) {
+        Some(dot) => match map.get(&path[start + dot + 1..]) {
+            Some(ext) => format!("{}.{}", &path[..start + dot], ext),
+            None => path.to_string(),
+        },
+        None => path.to_string(),
+    }
+}
+
+/// Returns the path with the slugs of the components, without the extension
+/// of the last one, like `meine-reise-2024` for `Meine Reise (2024)`
+fn slugify(path: &str) -> String {
+    let (dir, name) = path.rsplit_once('/').map_or(("", path), |(dir, name)| (dir, name));
+    let mut out: Vec<_> = dir.split('/').filter(|x| !x.is_empty()).map(slug).collect();
+    match name.rfind('.').filter(|dot| *dot > 0) {
+        Some(dot) => out.push(slug(&name[..dot]) + &name[dot..]),
+        None => out.push(slug(name)),
+    }
+
+    let mut out = out.join("/");
+    if path.starts_with('/') {
+        out.insert(0, '/');
+    }
+    out
+}
+
+/// The characters of path segments to percent-encode besides the non-ASCII
+/// ones, see https://url.spec.whatwg.org/#path-percent-encode-set
+const PATH_SEGMENT: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ').add(b'"').add(b'#').add(b'%').add(b'<').add(b'>').add(b'?').add(b'`').add(b'{').add(b'}');
+
+/// Returns the path with percent-encoded segments, e.g. for file names with
+/// `#` or `?`
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|x| percent_encoding::utf8_percent_encode(x, PATH_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns the URL with the percent-encoded non-ASCII characters decoded, like
+/// `https://jo-so.de/über.html`
+fn decode_utf8(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() {
+            let byte = u8::from_str_radix(&url[i + 1..i + 3], 16).unwrap_or_default();
+            if byte >= 0x80 {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| url.to_string())
+}
+
+/// Guesses the MIME type of a file from its extension
+fn mime_type(path: &str) -> &'static str {
+    let ext = path.rsplit_once('.').map_or("", |(_, ext)| ext).to_ascii_lowercase();
+    match ext.as_str() {
+        "aac" => "audio/aac",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "mp3" => "audio/mpeg",
+        "oga" | "ogg" | "opus" => "audio/ogg",
+        "wav" => "audio/wav",
+        "avi" => "video/x-msvideo",
+        "m4v" | "mp4" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "mov" => "video/quicktime",
+        "ogv" => "video/ogg",
+        "webm" => "video/webm",
+        "avif" => "image/avif",
+        "gif" => "image/gif",
+        "jpeg" | "jpg" => "image/jpeg",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "epub" => "application/epub+zip",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Which items to create
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    /// one item for each change of a file
+    Changes,
+    /// one item for each current file with its newest change
+    LastModified,
+    /// only items for new files
+    NewPages,
+    /// one item for the first commit of each author
+    Contributors,
+    /// one item for each tag
+    Tags,
+    /// one item for each forced update, reset or deletion in the reflog
+    Reflog,
+}
+
+/// How to handle deltas of binary files
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinaryPolicy {
+    /// like any other page
+    Page,
+    Skip,
+    /// with the titles `item-title-file-*`
+    Generic,
+    /// with the titles `item-title-file-*` and an enclosure of the file
+    Enclosure,
+}
+
+/// How to show the changes of a file in the description
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DiffStyle {
+    /// the patch with added and removed lines
+    Lines,
+    /// the changed words
+    Words,
+}
+
+/// The title of all items of the config entry `item-title`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TitleSource {
+    /// the subject of the commit
+    Subject,
+    /// the subject of the commit and the path of the page
+    SubjectPath,
+    /// the title of the front matter of Markdown pages
+    FrontMatter,
+}
+
+/// How to build the GUID of items
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GuidStrategy {
+    /// the link
+    Permalink,
+    /// a hash of commit and path
+    Opaque,
+    /// a hash of path, content and day, which stays the same when the history
+    /// gets rewritten
+    Content,
+    /// a UUID version 5 of commit and path in the namespace of the base URL
+    Uuid,
+}
+
+fn main() -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = clap::Command::new(clap::crate_name!())
+        .version(clap::crate_version!())
+        .author(clap::crate_authors!(", "))
+        .about(clap::crate_description!())
+        .arg(
+            Arg::new("conf")
+                .short('c')
+                .long("conf")
+                .num_args(1)
+                .value_name("FILE")
+                .required(true)
+                .value_hint(ValueHint::FilePath)
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .help("config file")
+        ).arg(
+            Arg::new("debug")
+                .short('d')
+                .long("debug")
+                .action(ArgAction::SetTrue)
+                .help("Print debug messages")
+        ).arg(
+            Arg::new("prefix")
+                .short('p')
+                .long("prefix")
+                .num_args(1)
+                .value_name("PREFIX")
+                .value_hint(ValueHint::Other)
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .help("PREFIX gets removed from the beginning of file names")
+        ).arg(
+            Arg::new("pretty")
+                .short('y')
+                .long("pretty")
+                .num_args(0..=1)
+                .require_equals(true)
+                .value_name("INDENT")
+                .default_missing_value("2")
+                .value_parser(|x: &str| match x {
+                    "tabs" | "tab" => Ok((b'\t', 1)),
+                    x => x.parse::<usize>().map(|n| (b' ', n))
+                        .map_err(|_| "expected a number of spaces or 'tabs'"),
+                })
+                .help("Pretty print output, indented by INDENT spaces or 'tabs'")
+        ).arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .num_args(1)
+                .value_name("FORMAT")
+                .value_parser(|x: &str| output::Format::from_name(x)
+                              .filter(|x| *x != output::Format::Template)
+                              .ok_or("expected rss, atom, jsonfeed, ndjson, html, h-feed, markdown, sitemap, ics or mbox"))
+                .help("Write all outputs in FORMAT: rss, atom, jsonfeed, ndjson, html, h-feed, markdown, sitemap, ics or mbox")
+        ).arg(
+            Arg::new("gzip")
+                .long("gzip")
+                .action(ArgAction::SetTrue)
+                .help("Write also a gzip compressed copy FILE.gz of each output file")
+        ).arg(
+            Arg::new("hook")
+                .long("hook")
+                .action(ArgAction::SetTrue)
+                .help("Add the pushed commits, read from stdin like a post-receive hook, to the RSS files")
+        ).arg(
+            Arg::new("serve")
+                .long("serve")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("hook")
+                .help("Serve the feeds over HTTP as configured in the config entry 'server'")
+        ).arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(ArgAction::SetTrue)
+                .help("Fail instead of writing a feed that violates RSS 2.0")
+        ).arg(
+            Arg::new("path")
+                .value_name("PATH")
+                .help("Path of the source file")
+                .required(true)
+                .num_args(1..)
+                .value_hint(ValueHint::AnyPath)
+        ).get_matches();
+
+    {
+        let mut logger = env_logger::builder();
+        match env::var("RUST_LOG_TIMESTAMP").as_deref() {
+            Ok("sec") => { logger.format_timestamp_secs(); }
+            Ok("micro") => { logger.format_timestamp_micros(); }
+            Ok("milli") => { logger.format_timestamp_millis(); }
+            Ok("nano") => { logger.format_timestamp_nanos(); }
+            Ok(_) => { logger.format_timestamp(None); }
+            _ => {},
+        }
+
+        if args.get_flag("debug") {
+            logger.filter_level(log::LevelFilter::Trace);
+        }
+
+        logger.init();
+    }
+
+    let conf = {
+        let txt = match args.get_one::<String>("conf").unwrap().as_str() {
+            "-" if args.get_flag("hook") => return Err("With --hook, the config can't be read from stdin".into()),
+            "-" => {
+                info!("Going to read config from stdin");
+                let mut buf = String::new();
+                io::stdin().read_to_string(&mut buf)?;
+                buf
+            }
+
+            path => {
+                info!("Going to read config file {}", path);
+                fs::read_to_string(path)?
+            }
+        };
+
+        YamlLoader::load_from_str(&txt)?.pop().unwrap()
+    };
+
+    let params = Params {
+        paths: args.get_many::<String>("path").unwrap().cloned().collect(),
+        prefix: args.get_one::<String>("prefix").cloned(),
+        strict: args.get_flag("strict"),
+        hook: args.get_flag("hook"),
+        pretty: args.get_one::<(u8, usize)>("pretty").copied(),
+        filter: None,
+        limit: None,
+        since: None,
+        format: args.get_one::<output::Format>("format").copied(),
+    };
+
+    tune_odb(&conf["odb"])?;
+    if args.get_flag("serve") {
+        return server::run(conf, params);
+    }
+
+    for feed in generate(&conf, &params)? {
+        output::write(&feed.output, &feed.vars, &feed.data, args.get_flag("gzip"))?;
+    }
+
+    Ok(())
+}
+
+/// Settings from the command line or a request to the server
+#[derive(Clone)]
+struct Params {
+    /// the pathspecs of the files
+    paths: Vec<String>,
+    prefix: Option<String>,
+    strict: bool,
+    hook: bool,
+    pretty: Option<(u8, usize)>,
+    /// a pathspec relative to strip-prefix the files must match
+    filter: Option<String>,
+    /// the maximum number of items
+    limit: Option<usize>,
+    /// the minimum time of the items
+    since: Option<i64>,
+    /// the format of all outputs
+    format: Option<output::Format>,
+}
+
+/// The entries of the config for the walk and the items, read once before
+/// the walk
+struct Settings<'a> {
+    conf: &'a Yaml,
+    // blobs above this size are never loaded to extract their content
+    max_blob_size: Option<usize>,
+    ignored_files: Option<Pathspec>,
+    binary_policy: BinaryPolicy,
+    guid_strategy: Option<GuidStrategy>,
+    outputs: Vec<output::Output>,
+    /// `section` or `author` for a feed of each
+    split_by: Option<&'static str>,
+    /// the number of items of the archive pages of rss and atom outputs
+    page_size: Option<usize>,
+    xml_opts: xml::Options,
+    duplicates: &'a str,
+    with_media_enclosures: bool,
+    with_thumbnails: bool,
+    /// add the rendered Markdown page as content of the items
+    with_content: bool,
+    /// add the tags of the front matter as categories of the items
+    with_tags: bool,
+    /// add the location of the front matter as georss:point of the items
+    with_georss: bool,
+    base_url: url::Url,
+    /// keep the UTF-8 characters in the paths of links with `url-encoding: utf-8`
+    utf8_urls: bool,
+    strip_prefix: &'a str,
+    /// the extensions of the pages in the URLs of the source files
+    extension_map: HashMap<String, String>,
+    /// the names of the pages, without extension, for the URLs of their
+    /// directories
+    directory_index: Vec<&'a str>,
+    /// turn the components of the paths of pages into slugs
+    with_slugs: bool,
+    /// the patterns of the paths with the replacements as paths of the pages
+    url_rewrites: Vec<(regex::Regex, String)>,
+    /// an additional filter of the paths, relative to strip-prefix
+    path_filter: Option<Pathspec>,
+    min_time: Option<i64>,
+    mode: Mode,
+    detect_renames: bool,
+    /// link the items of renamed files to the current path
+    follow_renames: bool,
+    /// commits with more changed files get one item
+    batch_threshold: Option<usize>,
+    batch_title: Cow<'a, str>,
+    forge: Option<forge::Forge>,
+    comments_text: &'a str,
+    /// use the link of the pull request of a commit as link of the item
+    pull_link: bool,
+    /// use the title and description of the pull request for the item
+    pull_title: bool,
+    /// the template of the link to the changes of the commit
+    compare_url: Option<&'a str>,
+    compare_text: &'a str,
+    /// `description` or `comments` for the place of the link to the changes
+    compare_link: &'a str,
+    /// the template of the link of removed pages in an archive
+    archive_url: Option<&'a str>,
+    /// `page`, `archive` or `none` for the link of removed pages
+    deleted_link: &'a str,
+    /// the template of the link to a commit
+    commit_url: Option<&'a str>,
+    backport_text: &'a str,
+    contributor_title: Cow<'a, str>,
+    /// the messages of the locale for the titles missing in the config
+    strings: i18n::Strings,
+    /// the names and homepages of the authors by email
+    authors: authors::Authors,
+    /// the template of the link of `<comments>` without forge or compare-link
+    item_comments_url: Option<&'a str>,
+    /// the feed as `<source>` of each item
+    item_source: Option<Source>,
+    /// `commit-message` or a template of the description of the items
+    item_description: Option<&'a str>,
+    /// the shell command for the descriptions of the items
+    description_command: Option<&'a str>,
+    /// the time after which description-command gets killed
+    command_timeout: Duration,
+    /// the Tera templates of the titles and descriptions of the items
+    templates: templates::Templates,
+    /// the number of characters after which the descriptions are truncated
+    description_max_length: Option<usize>,
+    /// the source of the titles of all items instead of the templates
+    title_source: Option<TitleSource>,
+    /// the titles of the items of the files matching the pathspecs
+    title_overrides: Vec<(Pathspec, &'a Yaml)>,
+    /// the categories of the items of the files matching the pathspecs
+    category_map: Vec<(Pathspec, Category)>,
+    /// the number of directories of the path for the category of items
+    category_depth: usize,
+    /// how to add the changes of modified pages to the description
+    item_diff: Option<DiffStyle>,
+    diff_max_lines: usize,
+}
+
+/// A change of a file in a commit, from which an item gets assembled
+struct Change<'c> {
+    repo: &'c Repository,
+    commit: &'c git2::Commit<'c>,
+    status: Delta,
+    path: &'c str,
+    /// the path before the change, e.g. of renamed files
+    old_path: Option<&'c str>,
+    binary: bool,
+    /// the blob of the file after the change, or before for removed files
+    blob: Oid,
+    /// the config entry of the title, like `item-title-page-new`
+    text: &'c str,
+    url_path: &'c str,
+    link: &'c url::Url,
+    /// the walked refs containing the commit, if there are several
+    branches: &'c [&'c str],
+    /// the abbreviated id of the commit
+    short_id: &'c str,
+    /// the HTML of the changes of the file for item-diff
+    changes: Option<&'c str>,
+    /// the number of added and removed lines, if a template needs them
+    stats: Option<(usize, usize)>,
+    /// the source of the Markdown page, if the config needs it
+    page_text: Option<&'c str>,
+}
+
+impl Change<'_> {
+    /// The path of the page as shown in titles like `/%p`
+    fn page_path(&self) -> String {
+        format!("/{}", self.url_path)
+    }
+
+    /// The path of the page as `%p` in titles and descriptions; the root index
+    /// has `/` instead of the empty path
+    fn text_path(&self) -> &str {
+        if self.url_path.is_empty() { "/" } else { self.url_path }
+    }
+
+    /// Expands the template of a title or description
+    fn expand_text(&self, template: &str, vars: &[(&str, String)]) -> String {
+        if !self.url_path.is_empty() {
+            return expand(template, vars);
+        }
+
+        let vars: Vec<_> = vars.iter()
+            .map(|(k, v)| (*k, if *k == "p" { self.text_path().to_string() } else { v.clone() }))
+            .collect();
+        expand(&root_template(template), &vars)
+    }
+
+    /// The title of the front matter of the Markdown page
+    fn page_title(&self) -> Option<String> {
+        let data = frontmatter::parse(self.page_text?, self.path);
+        data["title"].as_str().map(str::trim).filter(|x| !x.is_empty()).map(str::to_string)
+    }
+
+    /// The placeholders of the templates for the item
+    fn vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = vec![
+            ("p", self.url_path.to_string()),
+            ("H", self.commit.id().to_string()),
+            ("h", self.short_id.to_string()),
+            ("P", self.commit.parent_ids().next().map_or_else(String::new, |x| x.to_string())),
+            ("b", self.branches.join(", ")),
+            ("s", self.commit.summary().unwrap_or_default().to_string()),
+            ("an", self.commit.author().name().unwrap_or_default().to_string()),
+            ("ae", self.commit.author().email().unwrap_or_default().to_string()),
+            ("date", datetime(&self.commit.author().when()).to_rfc3339()),
+            ("t", self.page_title().unwrap_or_else(|| self.text_path().to_string())),
+        ];
+        if let Some((added, removed)) = self.stats {
+            vars.push(("+", added.to_string()));
+            vars.push(("-", removed.to_string()));
+        }
+        vars
+    }
+}
+
+impl<'a> Settings<'a> {
+    fn from_conf(conf: &'a Yaml, params: &'a Params) -> Result<Self, Box<dyn error::Error>> {
+        let ignored_files = if let Some(list) = conf["ignore-files"].as_vec() {
+            Some(Pathspec::new(list.iter().filter_map(|x| x.as_str()))?)
+        } else {
+            None
+        };
+
+        let binary_policy = match conf["binary-files"].as_str() {
+            None | Some("page") => BinaryPolicy::Page,
+            Some("skip") => BinaryPolicy::Skip,
+            Some("generic") => BinaryPolicy::Generic,
+            Some("enclosure") => BinaryPolicy::Enclosure,
+            Some(x) => return Err(format!("Invalid value of config entry 'binary-files': {}", x).into()),
+        };
+
+        let guid_strategy = match conf["guid"].as_str() {
+            None => None,
+            Some("permalink" | "url") => Some(GuidStrategy::Permalink),
+            Some("opaque" | "commit-path") => Some(GuidStrategy::Opaque),
+            Some("content") => Some(GuidStrategy::Content),
+            Some("uuid" | "uuid5") => Some(GuidStrategy::Uuid),
+            Some(x) => return Err(format!("Invalid value of config entry 'guid': {}", x).into()),
+        };
+
+        let min_time = match &conf["max-item-age"] {
+            Yaml::String(x) => {
+                let age = humantime::parse_duration(x)?;
+                info!("Dropping items older than {}", humantime::format_duration(age));
+                Some(chrono::Utc::now().timestamp() - age.as_secs() as i64)
+            }
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'max-item-age'".into()),
+        }.max(params.since);
+
+        let mut outputs = output::from_conf(conf)?;
+        if let Some(format) = params.format {
+            for out in &mut outputs {
+                out.format = format;
+            }
+        }
+        let split_by = match conf["split-by"].as_str() {
+            None => None,
+            Some("section") => Some("section"),
+            Some("author") => Some("author"),
+            Some(x) => return Err(format!("Invalid value of config entry 'split-by': {}", x).into()),
+        };
+        if let Some(split_by) = split_by {
+            let placeholder = format!("{{{}}}", split_by);
+            if outputs.iter().any(|x| !x.file.as_ref().is_some_and(|file| file.contains(&placeholder))) {
+                return Err(format!("With split-by, the file of each output must contain {}", placeholder).into());
+            }
+        }
+        let category_map = match &conf["categories"] {
+            Yaml::Hash(map) => map.iter()
+                .map(|(glob, value)| {
+                    let glob = glob.as_str().ok_or("Invalid path in config entry 'categories'")?;
+                    Ok((Pathspec::new([glob])?, conf_category(value, "categories")?))
+                })
+                .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?,
+            Yaml::BadValue => Vec::new(),
+            _ => return Err("Invalid value of config entry 'categories': expected a map".into()),
+        };
+        let command_timeout = match conf["description-command-timeout"].as_str() {
+            Some(x) => humantime::parse_duration(x)?,
+            None => Duration::from_secs(30),
+        };
+        let title_source = match conf["item-title"].as_str() {
+            None => None,
+            Some("commit-subject") => Some(TitleSource::Subject),
+            Some("commit-subject-path") => Some(TitleSource::SubjectPath),
+            Some("front-matter") => Some(TitleSource::FrontMatter),
+            Some(x) => return Err(format!("Invalid value of config entry 'item-title': {}", x).into()),
+        };
+        let title_overrides = match &conf["title-overrides"] {
+            Yaml::Array(list) => list.iter()
+                .map(|entry| {
+                    let spec = entry["pathspec"].as_str().ok_or("Missing pathspec in config entry 'title-overrides'")?;
+                    Ok((Pathspec::new([spec])?, entry))
+                })
+                .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?,
+            Yaml::BadValue => Vec::new(),
+            _ => return Err("Invalid value of config entry 'title-overrides': expected a list".into()),
+        };
+        let category_depth = match &conf["category-depth"] {
+            Yaml::Integer(n) if *n >= 0 => *n as usize,
+            Yaml::BadValue => 0,
+            _ => return Err("Invalid value of config entry 'category-depth'".into()),
+        };
+        let item_diff = match conf["item-diff"].as_str() {
+            None | Some("none") => None,
+            Some("html") => Some(DiffStyle::Lines),
+            Some("words") => Some(DiffStyle::Words),
+            Some(x) => return Err(format!("Invalid value of config entry 'item-diff': {}", x).into()),
+        };
+        let item_source = match &conf["item-source"] {
+            Yaml::String(url) => Some(Source {
+                url: url.clone(),
+                title: conf["channel-title"].as_str().map(str::to_string),
+            }),
+            src @ Yaml::Hash(_) => Some(Source {
+                url: src["url"].as_str().ok_or("Missing config entry 'item-source.url'")?.to_string(),
+                title: src["title"].as_str().or_else(|| conf["channel-title"].as_str()).map(str::to_string),
+            }),
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'item-source'".into()),
+        };
+        let description_max_length = match &conf["description-max-length"] {
+            Yaml::Integer(n) if *n > 0 => Some(*n as usize),
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'description-max-length'".into()),
+        };
+        let diff_max_lines = match &conf["item-diff-max-lines"] {
+            Yaml::Integer(n) if *n > 0 => *n as usize,
+            Yaml::BadValue => 50,
+            _ => return Err("Invalid value of config entry 'item-diff-max-lines'".into()),
+        };
+
+        let page_size = match &conf["page-size"] {
+            Yaml::Integer(n) if *n > 0 => Some(*n as usize),
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'page-size'".into()),
+        };
+        if page_size.is_some() && params.hook {
+            return Err("With --hook, the config entry 'page-size' can't be used".into());
+        }
+
+        let (pull_link, pull_title) = match conf["forge"]["pull-requests"].as_str() {
+            None => (false, false),
+            Some("link") => (true, false),
+            Some("title") => (false, true),
+            Some("both") => (true, true),
+            Some(x) => return Err(format!("Invalid value of config entry 'forge.pull-requests': {}", x).into()),
+        };
+
+        let compare_link = match conf["compare-link"].as_str() {
+            None => "description",
+            Some(x @ ("description" | "comments")) => x,
+            Some(x) => return Err(format!("Invalid value of config entry 'compare-link': {}", x).into()),
+        };
+
+        let mode = match conf["mode"].as_str() {
+            None | Some("changes") => Mode::Changes,
+            Some("last-modified") => Mode::LastModified,
+            Some("new-pages") => Mode::NewPages,
+            Some("contributors") => Mode::Contributors,
+            Some("tags") => Mode::Tags,
+            Some("reflog") => Mode::Reflog,
+            Some(x) => return Err(format!("Invalid value of config entry 'mode': {}", x).into()),
+        };
+
+        let archive_url = conf["archive-url"].as_str();
+        let deleted_link = match conf["deleted-link"].as_str() {
+            None => if archive_url.is_some() { "archive" } else { "page" },
+            Some("archive") if archive_url.is_none() =>
+                return Err("deleted-link: archive needs the config entry 'archive-url'".into()),
+            Some(x @ ("page" | "archive" | "none")) => x,
+            Some(x) => return Err(format!("Invalid value of config entry 'deleted-link': {}", x).into()),
+        };
+
+        let strip_prefix = params.prefix.as_deref()
+            .or_else(|| conf["strip-prefix"].as_str())
+            .unwrap_or("");
+        let path_filter = params.filter.as_ref()
+            .map(|x| Pathspec::new([format!("{}{}", strip_prefix, x)]))
+            .transpose()?;
+        let extension_map = match &conf["extension-map"] {
+            Yaml::Hash(map) => map.iter()
+                .map(|(from, to)| match (from.as_str(), to.as_str()) {
+                    (Some(from), Some(to)) => Ok((from.to_string(), to.to_string())),
+                    _ => Err("Invalid value in config entry 'extension-map'"),
+                })
+                .collect::<Result<HashMap<_, _>, _>>()?,
+            Yaml::BadValue => [("md".to_string(), "html".to_string())].into(),
+            _ => return Err("Invalid value of config entry 'extension-map': expected a map".into()),
+        };
+        let directory_index = match &conf["directory-index"] {
+            Yaml::Boolean(true) => vec!["index", "README"],
+            Yaml::Array(list) => list.iter()
+                .map(|x| x.as_str().ok_or("Invalid value in config entry 'directory-index'"))
+                .collect::<Result<_, _>>()?,
+            Yaml::Boolean(false) | Yaml::BadValue => Vec::new(),
+            _ => return Err("Invalid value of config entry 'directory-index'".into()),
+        };
+        let url_rewrites = match &conf["url-rewrites"] {
+            Yaml::Array(list) => list.iter()
+                .map(|rule| {
+                    let (Some(pattern), Some(replacement)) = (rule["pattern"].as_str(), rule["replacement"].as_str())
+                    else {
+                        return Err("Invalid rule in config entry 'url-rewrites': expected pattern and replacement".into());
+                    };
+                    let re = regex::Regex::new(pattern)
+                        .map_err(|err| format!("Invalid pattern in config entry 'url-rewrites': {}", err))?;
+                    Ok((re, replacement.to_string()))
+                })
+                .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?,
+            Yaml::BadValue => Vec::new(),
+            _ => return Err("Invalid value of config entry 'url-rewrites': expected a list".into()),
+        };
+
+        let follow_renames = conf["follow-renames"].as_bool().unwrap_or(false);
+
+        // last-modified has only one item per file
+        let batch_threshold = match &conf["batch-threshold"] {
+            Yaml::Integer(n) if *n >= 0 => Some(*n as usize).filter(|_| mode != Mode::LastModified),
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'batch-threshold'".into()),
+        };
+
+        let utf8_urls = match conf["url-encoding"].as_str() {
+            None | Some("percent") => false,
+            Some("utf-8") => true,
+            Some(x) => return Err(format!("Invalid value of config entry 'url-encoding': {}", x).into()),
+        };
+
+        let strings = i18n::Strings::from_conf(conf)?;
+
+        Ok(Settings {
+            conf,
+            max_blob_size: conf_size(&conf["max-blob-size"], "max-blob-size")?,
+            ignored_files,
+            binary_policy,
+            guid_strategy,
+            outputs,
+            split_by,
+            page_size,
+            xml_opts: xml::Options::from_conf(conf)?,
+            duplicates: conf["duplicate-items"].as_str().unwrap_or("warn"),
+            with_media_enclosures: conf["media-enclosures"].as_bool().unwrap_or(false),
+            with_thumbnails: conf["item-thumbnail"].as_bool().unwrap_or(false),
+            with_content: conf["item-content"].as_bool().unwrap_or(false),
+            with_tags: conf["front-matter-categories"].as_bool().unwrap_or(false),
+            with_georss: conf["front-matter-georss"].as_bool().unwrap_or(false),
+            base_url: url::Url::parse(conf["base-url"].as_str().unwrap())?,
+            utf8_urls,
+            strip_prefix,
+            extension_map,
+            directory_index,
+            with_slugs: conf["slugify"].as_bool().unwrap_or(false),
+            url_rewrites,
+            path_filter,
+            min_time,
+            mode,
+            // renamed files are no new pages
+            detect_renames: follow_renames
+                || conf["detect-renames"].as_bool().unwrap_or(mode == Mode::NewPages),
+            follow_renames,
+            batch_threshold,
+            batch_title: strings.text(conf, "item-title-batch").unwrap_or(Cow::Borrowed("%n pages updated")),
+            forge: forge::Forge::from_conf(&conf["forge"])?,
+            comments_text: conf["forge"]["comments-text"].as_str().unwrap_or("%n comments"),
+            pull_link,
+            pull_title,
+            compare_url: conf["compare-url"].as_str(),
+            compare_text: conf["compare-text"].as_str().unwrap_or("Changes"),
+            compare_link,
+            archive_url,
+            deleted_link,
+            commit_url: conf["commit-url"].as_str(),
+            backport_text: conf["backport-text"].as_str().unwrap_or("Backport of %h"),
+            contributor_title: strings.text(conf, "item-title-first-contribution")
+                .unwrap_or(Cow::Borrowed("First contribution by %an")),
+            strings,
+            authors: authors::Authors::from_conf(&conf["authors"])?,
+            item_comments_url: conf["item-comments-url"].as_str(),
+            item_description: conf["item-description"].as_str(),
+            templates: templates::Templates::from_conf(conf)?,
+            description_command: conf["description-command"].as_str(),
+            command_timeout,
+            description_max_length,
+            item_source,
+            title_source,
+            title_overrides,
+            category_map,
+            category_depth,
+            item_diff,
+            diff_max_lines,
+        })
+    }
+
+    /// Looks up the pull request of the commit, if the config asks for it
+    fn pull_request(&self, commit: &git2::Commit) -> Option<forge::PullRequest> {
+        if self.pull_link || self.pull_title {
+            let pr = self.forge.as_ref()?.pull_request(commit.id())?;
+            debug!("Commit {} belongs to {}", commit.id(), pr.url);
+            Some(pr)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the template of the title, the description or the Tera
+    /// templates use the diffstat
+    fn uses_stats(&self, title: Option<&str>) -> bool {
+        let uses_stats = |x: Option<&str>| x.is_some_and(|x| x.contains("%+") || x.contains("%-"));
+        uses_stats(title) || uses_stats(self.item_description) || !self.templates.is_empty()
+    }
+
+    /// Whether the titles, the description or the Tera templates use the
+    /// title of the front matter
+    fn uses_page_title(&self, title: Option<&str>) -> bool {
+        let uses_title = |x: Option<&str>| x.is_some_and(|x| x.contains("%t"));
+        self.title_source == Some(TitleSource::FrontMatter) || uses_title(title) || uses_title(self.item_description)
+            || !self.templates.is_empty()
+    }
+
+    /// Returns the context of the Tera templates for the change
+    fn template_context(&self, change: &Change) -> Result<tera::Context, Box<dyn error::Error>> {
+        let author = change.commit.author();
+        let email = author.email().unwrap_or_default();
+        let (added, removed) = change.stats.unwrap_or_default();
+        Ok(tera::Context::from_value(serde_json::json!({
+            "commit": {
+                "id": change.commit.id().to_string(),
+                "short_id": change.short_id,
+                "parent": change.commit.parent_ids().next().map(|x| x.to_string()),
+                "summary": change.commit.summary().unwrap_or_default(),
+                "message": change.commit.message().unwrap_or_default(),
+                "branches": change.branches,
+            },
+            "author": {"name": self.authors.name(email, author.name().unwrap_or_default()), "email": email},
+            "date": datetime(&author.when()).to_rfc3339(),
+            "path": change.path,
+            "title": change.page_title(),
+            "old_path": change.old_path,
+            "url": change.link.as_str(),
+            "status": format!("{:?}", change.status).to_lowercase(),
+            "binary": change.binary,
+            "stats": {"added": added, "removed": removed},
+        }))?)
+    }
+
+    /// Returns the template of the title of the first entry of title-overrides
+    /// matching the file, of the config entry `text` with the extension of the
+    /// file, like `item-title-page-new.jpg`, or of the config entry `text`
+    /// with the message of the locale as fallback
+    fn title_template(&self, path: &str, status: Delta, text: &str) -> Option<Cow<'a, str>> {
+        let status_key = match status {
+            Delta::Added => "added",
+            Delta::Deleted => "removed",
+            Delta::Renamed => "renamed",
+            _ => "modified",
+        };
+        self.title_overrides.iter()
+            .find(|(spec, entry)| spec.matches_path(Path::new(path), PathspecFlags::default())
+                  && !entry[status_key].is_badvalue())
+            .and_then(|(_, entry)| entry[status_key].as_str())
+            .or_else(|| {
+                let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+                self.conf[format!("{}.{}", text, ext).as_str()].as_str()
+            })
+            .map(Cow::Borrowed)
+            .or_else(|| self.strings.text(self.conf, text))
+    }
+
+    /// Returns the title of the item for the change; without one, it's the
+    /// subject of the commit or the path of the page, because readers show
+    /// items without title as blank entries
+    fn item_title(&self, change: &Change) -> Result<String, Box<dyn error::Error>> {
+        let mut title = None;
+        if self.pull_title {
+            title = self.pull_request(change.commit).map(|pr| pr.title);
+        }
+        if title.is_none() {
+            title = self.templates.title(&self.template_context(change)?)?;
+        }
+        if title.is_none() {
+            let subject = change.commit.summary().unwrap_or_default();
+            title = match self.title_source {
+                Some(TitleSource::Subject) => Some(subject.to_string()),
+                Some(TitleSource::SubjectPath) => Some(format!("{}: {}", subject, change.page_path())),
+                Some(TitleSource::FrontMatter) => change.page_title(),
+                None => None,
+            };
+        }
+        if title.is_none() {
+            title = self.title_template(change.path, change.status, change.text)
+                .map(|title| change.expand_text(&title, &change.vars()));
+        }
+
+        Ok(title.filter(|x| !x.trim().is_empty())
+           .or_else(|| change.commit.summary().filter(|x| !x.trim().is_empty()).map(str::to_string))
+           .unwrap_or_else(|| change.text_path().to_string()))
+    }
+
+    /// Returns the link of the item for the change; removed pages link to
+    /// the archive or nowhere
+    fn item_link(&self, change: &Change) -> Option<String> {
+        if self.pull_link {
+            if let Some(pr) = self.pull_request(change.commit) {
+                return Some(pr.url);
+            }
+        }
+
+        match (change.status, self.deleted_link, self.archive_url) {
+            (Delta::Deleted, "none", _) => None,
+            (Delta::Deleted, "archive", Some(template)) => {
+                let mut vars = change.vars();
+                vars.push(("u", change.link.to_string()));
+                Some(expand(template, &vars))
+            }
+            _ => Some(change.link.to_string()),
+        }
+    }
+
+    /// Returns the paragraphs of the description of the item for the change
+    fn item_description(&self, change: &Change) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let mut description = Vec::new();
+
+        match self.item_description {
+            Some("commit-message") => {
+                let html = message_html(change.commit.message().unwrap_or_default());
+                if !html.is_empty() {
+                    description.push(html);
+                }
+            }
+            Some(template) => {
+                let html_vars: Vec<_> = change.vars().into_iter().map(|(k, v)| (k, html_escape(&v))).collect();
+                description.push(change.expand_text(template, &html_vars));
+            }
+            None => {}
+        }
+        if let Some(html) = self.templates.description(&self.template_context(change)?)? {
+            description.push(html);
+        }
+        if let Some(command) = self.description_command {
+            let env = [
+                ("COMMIT", change.commit.id().to_string()),
+                ("FILE_PATH", change.path.to_string()),
+                ("STATUS", format!("{:?}", change.status).to_lowercase()),
+                ("URL", change.link.to_string()),
+            ];
+            // in the worktree, or the git dir of bare repositories, for `git show`
+            let dir = change.repo.workdir().unwrap_or_else(|| change.repo.path());
+            if let Some(html) = command_description(command, dir, &env, self.command_timeout)? {
+                description.push(html);
+            }
+        }
+
+        if let Some(html) = change.changes {
+            description.push(html.to_string());
+        }
+
+        if self.pull_title {
+            if let Some(text) = self.pull_request(change.commit).and_then(|pr| pr.description) {
+                description.push(format!("<p>{}</p>", html_escape(&text)));
+            }
+        }
+
+        if let Some(info) = self.forge.as_ref().and_then(|forge| forge.comments(change.commit.id())) {
+            if info.count > 0 {
+                let text = expand(self.comments_text, &[
+                    ("n", info.count.to_string()),
+                    ("r", info.reactions.to_string()),
+                ]);
+                description.push(format!(
+                    "<p><a href=\"{}\">{}</a></p>", html_escape(&info.url), html_escape(&text)
+                ));
+            }
+        }
+
+        if let Some(url) = self.compare_url(change) {
+            if self.compare_link == "description" {
+                description.push(format!(
+                    "<p><a href=\"{}\">{}</a></p>", html_escape(&url), html_escape(self.compare_text)
+                ));
+            }
+        }
+
+        if let Some(original) = change.commit.message().and_then(original_commit) {
+            debug!("Commit {} is a backport of {}", change.commit.id(), original);
+
+            let url = match (self.commit_url, &self.forge) {
+                (Some(template), _) => Some(expand(template, &[("H", original.to_string())])),
+                (None, Some(forge)) => Oid::from_str(original).ok().map(|id| forge.commit_url(id)),
+                (None, None) => None,
+            };
+            let text = html_escape(&expand(self.backport_text, &[("h", original[..original.len().min(7)].to_string())]));
+            description.push(match url {
+                Some(url) => format!("<p><a href=\"{}\">{}</a></p>", html_escape(&url), text),
+                None => format!("<p>{}</p>", text),
+            });
+        }
+
+        Ok(description)
+    }
+
+    /// Returns the HTML of the changes of the delta `idx` for item-diff
+    fn item_diff(&self, repo: &Repository, diff: &git2::Diff, idx: usize)
+                 -> Result<Option<String>, Box<dyn error::Error>>
+    {
+        Ok(match self.item_diff {
+            Some(DiffStyle::Lines) => git2::Patch::from_diff(diff, idx)?
+                .filter(|patch| patch.num_hunks() > 0)
+                .map(|patch| diff::html(&patch, self.diff_max_lines))
+                .transpose()?,
+            Some(DiffStyle::Words) => {
+                let delta = diff.get_delta(idx).ok_or("Missing delta of the diff")?;
+                let old = load_blob(repo, delta.old_file().id(), self.max_blob_size)?;
+                let new = load_blob(repo, delta.new_file().id(), self.max_blob_size)?;
+                match (old, new) {
+                    (Some(old), Some(new)) => Some(diff::words(&String::from_utf8_lossy(old.content()),
+                                                               &String::from_utf8_lossy(new.content()),
+                                                               self.diff_max_lines))
+                        .filter(|html| !html.is_empty()),
+                    _ => None,
+                }
+            }
+            None => None,
+        })
+    }
+
+    /// Returns the location of the front matter of the page for georss:point
+    fn item_point(&self, change: &Change) -> Option<(f64, f64)> {
+        let text = change.page_text.filter(|_| self.with_georss)?;
+        frontmatter::point(&frontmatter::parse(text, change.path))
+    }
+
+    /// Returns the categories of the item for the change
+    fn item_categories(&self, change: &Change) -> Vec<Category> {
+        let mut categories: Vec<_> = change.branches.iter()
+            .map(|name| Category { name: name.to_string(), domain: None })
+            .collect();
+        if let Some(dir) = path_category(change.path, self.strip_prefix, self.category_depth) {
+            categories.push(Category { name: dir.to_string(), domain: None });
+        }
+        for (spec, category) in &self.category_map {
+            if spec.matches_path(Path::new(change.path), PathspecFlags::default()) && !categories.contains(category) {
+                categories.push(category.clone());
+            }
+        }
+        if let Some(text) = change.page_text.filter(|_| self.with_tags) {
+            let data = frontmatter::parse(text, change.path);
+            for name in frontmatter::list(&data, "tags").into_iter().chain(frontmatter::list(&data, "categories")) {
+                let category = Category { name, domain: None };
+                if !categories.contains(&category) {
+                    categories.push(category);
+                }
+            }
+        }
+
+        if change.commit.message().and_then(original_commit).is_some() {
+            categories.push(Category { name: "backport".into(), domain: None });
+        }
+
+        categories
+    }
+
+    /// Returns the GUID of the item for the change
+    fn item_guid(&self, change: &Change) -> Result<Option<Guid>, Box<dyn error::Error>> {
+        Ok(match self.guid_strategy {
+            Some(GuidStrategy::Permalink) => Some(Guid { value: change.link.to_string(), permalink: true }),
+            Some(GuidStrategy::Opaque) => {
+                let id = Oid::hash_object(
+                    git2::ObjectType::Blob, format!("{}:{}", change.commit.id(), change.path).as_bytes()
+                )?;
+                Some(Guid { value: id.to_string(), permalink: false })
+            }
+            Some(GuidStrategy::Content) => {
+                // the author date survives a rewrite of the history, the
+                // commit date not
+                let day = change.commit.author().when().seconds().div_euclid(86400);
+                let id = Oid::hash_object(
+                    git2::ObjectType::Blob,
+                    format!("{}:{:?}:{}:{}", change.path.to_lowercase(), change.status, change.blob, day)
+                        .as_bytes()
+                )?;
+                Some(Guid { value: id.to_string(), permalink: false })
+            }
+            Some(GuidStrategy::Uuid) => {
+                let namespace = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, self.base_url.as_str().as_bytes());
+                let id = uuid::Uuid::new_v5(&namespace, format!("{}:{}", change.commit.id(), change.path).as_bytes());
+                Some(Guid { value: id.urn().to_string(), permalink: false })
+            }
+            None => None,
+        })
+    }
+
+    /// Returns the item for the first contribution of the author of the commit;
+    /// `author` is the author for the item
+    fn contributor_item(&self, commit: &git2::Commit, author: &str) -> Result<rss::Item, Box<dyn error::Error>> {
+        let signature = commit.author();
+        let email = signature.email().unwrap_or_default();
+        let vars = [
+            ("an", signature.name().unwrap_or_default().to_string()),
+            ("ae", email.to_string()),
+            ("H", commit.id().to_string()),
+        ];
+
+        let link = match (self.commit_url, &self.forge) {
+            (Some(template), _) => expand(template, &vars),
+            (None, Some(forge)) => forge.commit_url(commit.id()),
+            (None, None) => self.base_url.to_string(),
+        };
+        let guid = if self.guid_strategy.is_some() {
+            let id = Oid::hash_object(git2::ObjectType::Blob, format!("contributor:{}", email).as_bytes())?;
+            Some(Guid { value: id.to_string(), permalink: false })
+        } else {
+            None
+        };
+
+        Ok(ItemBuilder::default()
+            .author(Some(author.to_string()))
+            .pub_date(Some(rfc822_time(&signature.when())))
+            .title(Some(expand(&self.contributor_title, &vars)))
+            .link(Some(link))
+            .description(commit.summary().map(|x| format!("<p>{}</p>", html_escape(x))))
+            .guid(guid)
+            .build())
+    }
+
+    /// Returns the page with the comments on the commit, the link to the
+    /// changes with `compare-link: comments` or the link of item-comments-url
+    fn item_comments(&self, change: &Change) -> Option<String> {
+        if self.compare_link == "comments" {
+            if let Some(url) = self.compare_url(change) {
+                return Some(url);
+            }
+        }
+
+        self.forge.as_ref()
+            .and_then(|forge| forge.comments(change.commit.id()))
+            .map(|info| info.url)
+            .or_else(|| self.item_comments_url.map(|template| expand(template, &change.vars())))
+    }
+
+    /// Returns the link to the changes of the commit; commits without parent
+    /// have nothing to compare
+    fn compare_url(&self, change: &Change) -> Option<String> {
+        let template = self.compare_url?;
+        change.commit.parent_ids().next()?;
+        Some(expand(template, &change.vars()))
+    }
+}
+
+/// An output file of a channel with its content
+struct Rendered<'a> {
+    output: output::Output,
+    /// the placeholders of the file name
+    vars: BTreeMap<&'a str, String>,
+    data: Vec<u8>,
+}
+
+/// Walks the history and renders the outputs of the config
+fn generate<'a>(conf: &'a Yaml, params: &Params) -> Result<Vec<Rendered<'a>>, Box<dyn error::Error>> {
+    let settings = Settings::from_conf(conf, params)?;
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.ignore_filemode(true)
+        .ignore_submodules(true)
+        .ignore_whitespace(true);
+
+    if let Some(size) = settings.max_blob_size {
+        // libgit2 treats larger files as binary and doesn't load them for diffs
+        diff_opts.max_size(size as i64);
+    }
+
+    for e in params.paths.iter() {
+        info!("using path filter {}", e);
+        diff_opts.pathspec(e);
+    }
+
+    // If all pathspecs start with a fixed path, commits can be skipped without
+    // a diff, if the tree entries of these paths are the same as in the parent
+    let path_prefixes = params.paths.iter()
+        .map(|e| pathspec_prefix(e))
+        .collect::<Option<Vec<_>>>();
+    debug!("Tree prefixes for skipping commits: {:?}", path_prefixes);
+
+    let mut diff_similar_opts = DiffFindOptions::default();
+    diff_similar_opts.renames(true);
+
+    let repo = if let Some(path) = conf["repo"].as_str() {
+        info!("Opening git repository {}", path);
+        Repository::open(path)?
+    } else {
+        let repo = Repository::open_from_env()?;
+        info!("Successfully opened git repository {}", repo.path().display());
+        repo
+    };
+
+    // libgit2 loads the multi-pack-index by itself, if it exists
+    if repo.path().join("objects/pack/multi-pack-index").exists() {
+        debug!("Repository has a multi-pack-index");
+    } else if conf["odb"].as_hash().is_some() {
+        info!("Repository has no multi-pack-index; run `git multi-pack-index write` \
+               to speed up object lookups in repositories with many packs");
+    }
+
+    let base_url = &settings.base_url;
+    let strip_prefix = settings.strip_prefix;
+    let max_blob_size = settings.max_blob_size;
+
+    let content = content::Reader::from_conf(conf, &repo, max_blob_size)?;
+
+    let mut items = Vec::new();
+
+    // the walked refs by name and commit
+    // with --hook: the pushed refs and their old commits, which were already
+    // walked
+    let mut hidden = Vec::new();
+    let refs = if params.hook {
+        let head = repo.head()?;
+        let mut refs = Vec::new();
+        for RefUpdate { name, old, new } in hook_updates(io::stdin().lock(), &conf["refs"], head.name().unwrap_or("HEAD"))? {
+            info!("Adding the commits pushed to {}: {}..{}", name, old, new);
+            if !old.is_zero() {
+                hidden.push(old);
+            }
+            refs.push((name, new));
+        }
+        refs
+    } else {
+        walked_refs(&conf["refs"], &repo)?
+    };
+    info!("Walking the history of {}", refs.iter().map(|(name, _)| name.as_str())
+          .collect::<Vec<_>>().join(", "));
+
+    // for last-modified: the current files on the walked refs, of which the
+    // newest change is used
+    let mut current_files = HashSet::new();
+    if settings.mode == Mode::LastModified {
+        let pathspec = Pathspec::new(&params.paths)?;
+        for (_, id) in &refs {
+            let tree = repo.find_commit(*id)?.tree()?;
+            for path in pathspec.match_tree(&tree, PathspecFlags::default())?.entries() {
+                let ignored = settings.ignored_files.as_ref()
+                    .is_some_and(|ign| ign.matches_path(Path::new(OsStr::from_bytes(path)), PathspecFlags::default()));
+                if !ignored {
+                    current_files.insert(String::from_utf8_lossy(path).into_owned());
+                }
+            }
+        }
+        info!("Looking for the last change of {} files", current_files.len());
+    }
+    let mut seen_files = HashSet::new();
+
+    // for contributors: the first commit of each author by email
+    let mut contributors = HashMap::new();
+
+    // old path → current path of renamed files, for links to the current page
+    let mut renames = HashMap::<String, String>::new();
+
+    let mut revwalk = repo.revwalk()?;
+    if settings.min_time.is_some() || settings.mode == Mode::LastModified {
+        // to stop the walk at the first commit older than the limit and to
+        // see the newest change of a file first
+        revwalk.set_sorting(git2::Sort::TIME)?;
+    }
+    if settings.follow_renames {
+        // a rename must be seen before the older changes of the file
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    }
+    for (_, id) in &refs {
+        revwalk.push(*id)?;
+    }
+    for id in &hidden {
+        revwalk.hide(*id)?;
+    }
+
+    // the same change on several refs, e.g. cherry-picked commits, gets only
+    // one item for its oldest commit; last-modified keeps the newest change
+    let dedup_patches = refs.len() > 1 && settings.mode != Mode::LastModified;
+    let mut patch_commits = HashMap::<Oid, ((i64, i64), Oid)>::new();
+    let mut commit_patches = HashMap::<Oid, Oid>::new();
+    if settings.mode == Mode::Tags {
+        items = refs::tags(&repo, conf, base_url)?;
+        info!("Found {} tags", items.len());
+    } else if settings.mode == Mode::Reflog {
+        items = refs::reflog(&repo, conf, base_url)?;
+        info!("Found {} destructive updates in the reflog", items.len());
+    }
+
+    for id in revwalk {
+        if settings.mode == Mode::Tags || settings.mode == Mode::Reflog {
+            break;
+        }
+        if settings.mode == Mode::LastModified && seen_files.len() == current_files.len() {
+            debug!("Found the last change of all files");
+            break;
+        }
+
+        let commit = repo.find_commit(id?)?;
+        if let Some(min_time) = settings.min_time {
+            if commit.time().seconds() < min_time {
+                // in topological order, newer commits of other branches may follow
+                if settings.follow_renames {
+                    debug!("Skipping commit {}, because it is older than max-item-age", commit.id());
+                    continue;
+                }
+                debug!("Stopping at commit {}, because it is older than max-item-age", commit.id());
+                break;
+            }
+            if commit.author().when().seconds() < min_time {
+                debug!("Skipping commit {}, because it is older than max-item-age", commit.id());
+                continue;
+            }
+        }
+        if commit.parent_count() > 1 {
+            debug!("Skipping merge commit {}", commit.id());
+            continue;
+        }
+        if commit.message().is_some_and(|msg| msg.contains("\nno-rss\n")) {
+            info!("Skipping commit {}, because of \"no-rss\"", commit.id());
+            continue;
+        }
+
+        // the refs containing the commit
+        let branches = if refs.len() > 1 {
+            let mut names = Vec::new();
+            for (name, id) in &refs {
+                if *id == commit.id() || repo.graph_descendant_of(*id, commit.id())? {
+                    names.push(name.as_str());
+                }
+            }
+            names
+        } else {
+            Vec::new()
+        };
+        let short_id = commit.as_object().short_id()?;
+
+        let author = commit.author();
+        let author_date = rfc822_time(&author.when());
+        let author_name = settings.authors.name(author.email().unwrap(), author.name().unwrap());
+        let author_email = author.email().unwrap().to_string();
+        let author = author_email.clone() + " (" + &author_name + ")";
+
+        let parent_tree = if commit.parent_count() == 1 {
+            Some(commit.parent(0)?.tree()?)
+        } else {
+            None
+        };
+
+        let tree = commit.tree()?;
+
+        if let Some(ref prefixes) = path_prefixes {
+            let unchanged = prefixes.iter().all(|prefix| {
+                let entry_id = |tree: &git2::Tree| {
+                    tree.get_path(Path::new(prefix)).ok().map(|e| e.id())
+                };
+                entry_id(&tree) == parent_tree.as_ref().and_then(entry_id)
+            });
+
+            if unchanged {
+                trace!("Skipping commit {}, because no path prefix changed", commit.id());
+                continue;
+            }
+        }
+
+        let first_item = items.len();
+        let mut diff = repo.diff_tree_to_tree(
+            parent_tree.as_ref(), Some(&tree), Some(&mut diff_opts)
+        )?;
+        if settings.detect_renames {
+            diff.find_similar(Some(&mut diff_similar_opts))?;
+        }
+
+        if dedup_patches && diff.deltas().len() > 0 {
+            let patch_id = diff.patchid(None)?;
+            // cherry-picked commits have the same author date
+            let time = (commit.author().when().seconds(), commit.time().seconds());
+            let oldest = patch_commits.entry(patch_id).or_insert((time, commit.id()));
+            if time <= oldest.0 {
+                *oldest = (time, commit.id());
+            }
+            commit_patches.insert(commit.id(), patch_id);
+        }
+
+        if settings.mode == Mode::Contributors {
+            let path = diff.deltas()
+                .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+                .find(|path| !settings.ignored_files.as_ref()
+                      .is_some_and(|ign| ign.matches_path(path, PathspecFlags::default())));
+
+            // the walk is newest first, so replace the entry for older commits
+            if let Some(path) = path {
+                let newer = contributors.get(&author_email)
+                    .is_some_and(|e: &Entry| e.time.seconds() < commit.author().when().seconds());
+                if !newer {
+                    contributors.insert(author_email.clone(), Entry {
+                        time: commit.author().when(),
+                        commit: commit.id(),
+                        status: Delta::Added,
+                        path: path.to_str().unwrap().to_string(),
+                        page: None,
+                        author_name: author_name.clone(),
+                        author_email: author_email.clone(),
+                        author_uri: None,
+                        item: settings.contributor_item(&commit, &author)?,
+                    });
+                }
+            }
+            continue;
+        }
+
+        for (delta_idx, delta) in diff.deltas().enumerate() {
+            trace!("{} {:?} {:?}, {:?}",
+                   commit.id(),
+                   delta.status(),
+                   delta.old_file().path(),
+                   delta.new_file().path(),
+            );
+
+            if settings.follow_renames && delta.status() == Delta::Renamed {
+                if let (Some(old), Some(new)) = (delta.old_file().path(), delta.new_file().path()) {
+                    let (old, new) = (old.to_str().unwrap(), new.to_str().unwrap());
+                    let current = renames.get(new).cloned().unwrap_or_else(|| new.to_string());
+                    debug!("File {} was renamed to {}", old, current);
+                    renames.insert(old.to_string(), current);
+                }
+            }
+
+            if settings.mode == Mode::NewPages && delta.status() != Delta::Added {
+                trace!("Skipping delta {:?} of {:?}, because it is no new file",
+                       delta.status(), delta.new_file().path());
+                continue;
+            }
+
+            let file;
+            let text;
+            match delta.status() {
+                Delta::Added => {
+                    file = delta.new_file();
+                    text = "item-title-page-new";
+                }
+
+                Delta::Deleted => {
+                    file = delta.old_file();
+                    text = "item-title-page-removed";
+                }
+
+                Delta::Modified => {
+                    file = delta.new_file();
+                    text = "item-title-page-modified"
+                }
+
+                Delta::Renamed => {
+                    file = delta.new_file();
+                    text = "item-title-page-renamed"
+                }
+
+                st => {
+                    warn!(
+                        "Unhandled diff state {:?} for commit {} between {:?} and {:?}",
+                        st,
+                        commit.id(),
+                        delta.old_file().path(),
+                        delta.new_file().path(),
+                    );
+                    continue;
+                }
+            }
+
+            let path = file.path().unwrap();
+
+            if let Some(ref ign) = settings.ignored_files {
+                if ign.matches_path(path, PathspecFlags::default()) {
+                    info!("Skipping delta of ignored file {} in commit {}",
+                          path.display(), commit.id());
+                    continue;
+                }
+            }
+            if settings.path_filter.as_ref().is_some_and(|x| !x.matches_path(path, PathspecFlags::default())) {
+                trace!("Skipping delta of {}, because of the path filter", path.display());
+                continue;
+            }
+
+            let binary = settings.binary_policy != BinaryPolicy::Page
+                && is_binary(&repo, &file, max_blob_size)?;
+            if binary && settings.binary_policy == BinaryPolicy::Skip {
+                info!("Skipping delta of binary file {} in commit {}",
+                      path.display(), commit.id());
+                continue;
+            }
+
+            let file_text = text.replace("-page-", "-file-");
+            let text = if binary && !conf[file_text.as_str()].is_badvalue() {
+                file_text
+            } else {
+                text.to_string()
+            };
+
+            let path = path.to_str().unwrap();
+
+            if settings.mode == Mode::LastModified
+                && (!current_files.contains(path) || !seen_files.insert(path.to_string()))
+            {
+                trace!("Skipping older change of {} in commit {}", path, commit.id());
+                continue;
+            }
+            let to_url_path = |path: &str| {
+                if let Some((re, replacement)) = settings.url_rewrites.iter().find(|(re, _)| re.is_match(path)) {
+                    return re.replace(path, replacement.as_str()).trim_start_matches('/').to_string();
+                }
+
+                let path = path.strip_prefix(strip_prefix).unwrap_or(path);
+                if binary {
+                    return path.to_string();
+                }
+
+                let (dir, name) = path.rsplit_once('/').map_or(("", path), |(dir, name)| (dir, name));
+                let stem = name.split_once('.').map_or(name, |(stem, _)| stem);
+                let path = if settings.directory_index.contains(&stem) {
+                    if dir.is_empty() { String::new() } else { format!("{}/", dir) }
+                } else {
+                    map_extension(path, &settings.extension_map)
+                };
+                if settings.with_slugs { slugify(&path) } else { path }
+            };
+            let url_path = to_url_path(path);
+
+            let link = match renames.get(path) {
+                Some(current) => base_url.join(&encode_path(&to_url_path(current)))?,
+                None => base_url.join(&encode_path(&url_path))?,
+            };
+
+            let is_media = || {
+                let mime = mime_type(path);
+                ["audio/", "image/", "video/"].iter().any(|x| mime.starts_with(x))
+            };
+
+            let enclosure = if (binary && settings.binary_policy == BinaryPolicy::Enclosure
+                                && delta.status() != Delta::Deleted)
+                || (settings.with_media_enclosures && delta.status() == Delta::Added && is_media())
+            {
+                let (size, _) = repo.odb()?.read_header(file.id())?;
+                Some(Enclosure {
+                    url: link.to_string(),
+                    length: size.to_string(),
+                    mime_type: mime_type(path).to_string(),
+                })
+            } else {
+                None
+            };
+
+            let title_template = settings.title_template(path, delta.status(), &text);
+
+            // the source of Markdown pages for the content, categories, location, title and thumbnail
+            let page_text = if (settings.with_content || settings.with_tags || settings.with_georss
+                                || settings.with_thumbnails || settings.uses_page_title(title_template.as_deref()))
+                && path.ends_with(".md") && !binary && delta.status() != Delta::Deleted
+            {
+                content.read(&repo, path, file.id())?.map(|x| String::from_utf8_lossy(&x).into_owned())
+            } else {
+                None
+            };
+
+            let changes = if !binary && matches!(delta.status(), Delta::Modified | Delta::Renamed) {
+                settings.item_diff(&repo, &diff, delta_idx)?
+            } else {
+                None
+            };
+
+            // the patch is only needed for the diffstat
+            let stats = if settings.uses_stats(title_template.as_deref()) {
+                let (_, added, removed) = match git2::Patch::from_diff(&diff, delta_idx)? {
+                    Some(patch) => patch.line_stats()?,
+                    None => (0, 0, 0),
+                };
+                Some((added, removed))
+            } else {
+                None
+            };
+
+            let change = Change {
+                repo: &repo,
+                commit: &commit,
+                status: delta.status(),
+                path,
+                old_path: delta.old_file().path().and_then(|x| x.to_str()),
+                binary,
+                blob: file.id(),
+                text: &text,
+                url_path: &url_path,
+                link: &link,
+                branches: &branches,
+                short_id: short_id.as_str().unwrap_or_default(),
+                changes: changes.as_deref(),
+                stats,
+                page_text: page_text.as_deref(),
+            };
+
+            let description = settings.item_description(&change)?;
+
+            let item_content = page_text.as_deref()
+                .filter(|_| settings.with_content)
+                .map(|text| markdown::to_html(text, &link));
+
+            let mut extensions = ExtensionMap::new();
+
+            if let Some((lat, lon)) = settings.item_point(&change) {
+                extensions.entry("georss".into()).or_default()
+                    .insert("point".into(), vec![Extension {
+                        name: "georss:point".into(),
+                        value: Some(format!("{} {}", lat, lon)),
+                        ..Default::default()
+                    }]);
+            }
+
+            if let Some(text) = page_text.as_deref().filter(|_| settings.with_thumbnails) {
+                let image = markdown::first_image(text).map(|src| link.join(src)).transpose()?;
+
+                if let Some(image) = image {
+                    debug!("Using thumbnail {} for {}:{}", image, commit.id(), path);
+                    let media = extensions.entry("media".into()).or_default();
+                    media.insert("thumbnail".into(), vec![Extension {
+                        name: "media:thumbnail".into(),
+                        attrs: [("url".to_string(), image.to_string())].into(),
+                        ..Default::default()
+                    }]);
+                    media.insert("content".into(), vec![Extension {
+                        name: "media:content".into(),
+                        attrs: [
+                            ("url".to_string(), image.to_string()),
+                            ("medium".to_string(), "image".to_string()),
+                            ("type".to_string(), mime_type(image.path()).to_string()),
+                        ].into(),
+                        ..Default::default()
+                    }]);
+                }
+            }
+
+            items.push(
+                Entry {
+                    time: commit.author().when(),
+                    commit: commit.id(),
+                    status: delta.status(),
+                    path: path.to_string(),
+                    page: Some(link.to_string()),
+                    author_name: author_name.clone(),
+                    author_email: author_email.clone(),
+                    author_uri: None,
+                    item: ItemBuilder::default()
+                        .author(Some(author.clone()))
+                        .categories(settings.item_categories(&change))
+                        .pub_date(Some(author_date.clone()))
+                        .title(Some(settings.item_title(&change)?))
+                        .link(settings.item_link(&change))
+                        .description(match (description.is_empty(), settings.description_max_length) {
+                            (true, _) => None,
+                            (false, Some(max)) => Some(truncate_html(&description.join("\n"), max)),
+                            (false, None) => Some(description.join("\n")),
+                        })
+                        .content(item_content)
+                        .comments(settings.item_comments(&change))
+                        .enclosure(enclosure)
+                        .guid(settings.item_guid(&change)?)
+                        .extensions(extensions)
+                        .build(),
+                }
+            );
+            debug!("New rss item for {}:{}", commit.id(), path)
+        }
+
+        if settings.batch_threshold.is_some_and(|n| items.len() - first_item > n) {
+            let entries = items.split_off(first_item);
+            debug!("Combining {} items of commit {}", entries.len(), commit.id());
+            items.push(batch_entry(entries, &settings.batch_title, base_url, settings.guid_strategy.is_some())?);
+        }
+    }
+
+    if let Some(ref forge) = settings.forge {
+        forge.save()?;
+    }
+
+    items.extend(contributors.into_values());
+
+    items.retain(|e| {
+        let keep = commit_patches.get(&e.commit).is_none_or(|id| patch_commits[id].1 == e.commit);
+        if !keep {
+            debug!("Skipping item for {}:{}, because an older commit has the same change",
+                   e.commit, e.path);
+        }
+        keep
+    });
+
+    // the walk is newest first; changes in the same second keep their order
+    items.reverse();
+    items.sort_by_key(|e| e.time);
+    let summaries = summary::entries(&conf["summary"], &items, strip_prefix, base_url)?;
+    if !summaries.is_empty() {
+        info!("Adding {} summary items", summaries.len());
+        items.extend(summaries);
+        items.sort_by_key(|e| e.time);
+    }
+    resolve_duplicates(&mut items, settings.duplicates)?;
+
+    if let Some(limit) = params.limit {
+        // keep the newest items
+        items.drain(..items.len().saturating_sub(limit));
+    }
+
+    settings.authors.apply(&mut items);
+    let mut plugins = plugin::Plugins::from_conf(conf)?;
+    plugins.filter_items(&mut items)?;
+    podcast::apply(&conf["podcast"], &mut items)?;
+    if let Some(sanitizer) = sanitize::from_conf(&conf["sanitize-html"])? {
+        sanitize::apply(&sanitizer, &mut items);
+    }
+    match &conf["obfuscate-email"] {
+        Yaml::Boolean(true) => {
+            for e in &mut items {
+                e.author_email = authors::obfuscate_email(&e.author_email);
+                if e.item.author().is_some() {
+                    e.item.set_author(format!("{} ({})", e.author_email, e.author_name));
+                }
+            }
+        }
+        // the name is kept in dc:creator
+        Yaml::String(x) if x == "drop" => use_dc_creator(&mut items),
+        Yaml::Boolean(false) | Yaml::BadValue => {}
+        _ => return Err("Invalid value of config entry 'obfuscate-email'".into()),
+    }
+    if conf["dc-creator"].as_bool().unwrap_or(false) {
+        use_dc_creator(&mut items);
+    }
+
+    if settings.utf8_urls {
+        for e in &mut items {
+            if let Some(link) = e.item.link().map(decode_utf8) {
+                e.item.set_link(link);
+            }
+            if let Some(guid) = e.item.guid.as_mut().filter(|x| x.is_permalink()) {
+                guid.value = decode_utf8(&guid.value);
+            }
+            if let Some(enclosure) = e.item.enclosure.as_mut() {
+                enclosure.url = decode_utf8(&enclosure.url);
+            }
+            e.page = e.page.as_deref().map(decode_utf8);
+        }
+    }
+
+    if let Some(ref source) = settings.item_source {
+        for e in &mut items {
+            e.item.set_source(source.clone());
+        }
+    }
+
+    if params.strict {
+        check_strict(conf, &items, base_url)?;
+    }
+
+    let opts = output::Options {
+        pretty: params.pretty,
+        xml: settings.xml_opts,
+        mail: output::MailOptions::from_conf(conf)?,
+    };
+
+    let mut rendered = Vec::new();
+    let mut feeds = Vec::new();
+    let mut vars = BTreeMap::new();
+    vars.insert("lang", conf["language"].as_str().unwrap_or("").to_string());
+
+    for (key, group) in split_entries(items, settings.split_by, strip_prefix) {
+        if let (Some(split_by), Some(key)) = (settings.split_by, key) {
+            vars.insert(split_by, key);
+        }
+
+        let mut chan = build_channel(conf, &group)?;
+        plugins.finalize_channel(&mut chan)?;
+        for out in &settings.outputs {
+            if let Some(url) = out.url.as_deref().filter(|_| out.format.is_feed()) {
+                let title = match settings.split_by.and_then(|x| vars.get(x)) {
+                    Some(key) => format!("{} – {}", chan.title(), key),
+                    None => chan.title().to_string(),
+                };
+                feeds.push(output::Feed {
+                    title,
+                    url: output::expand_path(url, &vars)?,
+                    html_url: chan.link().to_string(),
+                });
+            }
+
+            let paged = matches!(out.format, output::Format::Rss | output::Format::Atom);
+            if let Some(size) = settings.page_size.filter(|_| paged) {
+                for page in output::pages(out, group.len(), size)? {
+                    let entries = &group[page.range.clone()];
+                    let mut chan = build_channel(conf, entries)?;
+                    plugins.finalize_channel(&mut chan)?;
+                    page.add_links(&mut chan, &vars)?;
+                    let data = output::render(&page.output, &chan, entries, &opts, &vars)?;
+                    rendered.push(Rendered { output: page.output, vars: vars.clone(), data });
+                }
+                continue;
+            }
+
+            let mut chan = chan.clone();
+            if params.hook {
+                if group.is_empty() {
+                    continue;
+                }
+                output::merge_existing(out, &vars, &mut chan, settings.min_time, params.limit)?;
+            }
+            let data = output::render(out, &chan, &group, &opts, &vars)?;
+            rendered.push(Rendered { output: out.clone(), vars: vars.clone(), data });
+        }
+    }
+
+    if let Some((output, data)) = output::opml(conf, &feeds) {
+        info!("Listing {} feeds in the OPML output", feeds.len());
+        rendered.push(Rendered { output, vars: BTreeMap::new(), data });
+    }
+
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pathspec_prefixes() {
+        assert_eq!(pathspec_prefix("src/blog/**/*.md"), Some("src/blog"));
+        assert_eq!(pathspec_prefix("src/blog/"), Some("src/blog"));
+        assert_eq!(pathspec_prefix("src/index.md"), Some("src/index.md"));
+        assert_eq!(pathspec_prefix("src/a?/x"), Some("src"));
+        assert_eq!(pathspec_prefix("./src"), Some("src"));
+        assert_eq!(pathspec_prefix("./src/*.md"), Some("src"));
+        assert_eq!(pathspec_prefix("."), None);
+        assert_eq!(pathspec_prefix("./"), None);
+        assert_eq!(pathspec_prefix("*.md"), None);
+        assert_eq!(pathspec_prefix(":(exclude)src/draft"), None);
+        assert_eq!(pathspec_prefix(""), None);
+    }
+
+    fn yaml(text: &str) -> Yaml {
+        YamlLoader::load_from_str(text).unwrap().remove(0)
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(conf_size(&yaml("42"), "x").unwrap(), Some(42));
+        assert_eq!(conf_size(&yaml("'8k'"), "x").unwrap(), Some(8 << 10));
+        assert_eq!(conf_size(&yaml("'16 M'"), "x").unwrap(), Some(16 << 20));
+        assert_eq!(conf_size(&yaml("'1G'"), "x").unwrap(), Some(1 << 30));
+        assert_eq!(conf_size(&Yaml::BadValue, "x").unwrap(), None);
+        assert!(conf_size(&yaml("'1T'"), "x").is_err());
+        assert!(conf_size(&yaml("-1"), "x").is_err());
+    }
+
+    #[test]
+    fn odb_options() {
+        let conf = YamlLoader::load_from_str("mwindow-file-limit: 0\n\
+                                              cache-object-limit:\n  blob: 0\n")
+            .unwrap().remove(0);
+        tune_odb(&conf).unwrap();
+    }
+
+    /// Creates an empty repository in the temporary directory
+    fn empty_repo(name: &str) -> (std::path::PathBuf, Repository) {
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        (dir, repo)
+    }
+
+    #[test]
+    fn binary_files() {
+        let (dir, repo) = empty_repo("binary");
+        let mut builder = repo.treebuilder(None).unwrap();
+        for (name, data) in [("a.md", &b"# A\n"[..]), ("b.png", b"\x89PNG\0\x01"), ("c.md", b"# Long text\n")] {
+            builder.insert(name, repo.blob(data).unwrap(), 0o100644).unwrap();
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let diff = repo.diff_tree_to_tree(None, Some(&tree), None).unwrap();
+        let binary: Vec<_> = diff.deltas()
+            .map(|d| is_binary(&repo, &d.new_file(), Some(8)).unwrap())
+            .collect();
+        // c.md is larger than the limit
+        assert_eq!(binary, [false, true, true]);
+        assert!(load_blob(&repo, diff.get_delta(2).unwrap().new_file().id(), Some(8)).unwrap().is_none());
+
+        assert_eq!(mime_type("a/b.PNG"), "image/png");
+        assert_eq!(mime_type("talk.opus"), "audio/ogg");
+        assert_eq!(mime_type("README"), "application/octet-stream");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn generator() {
+        assert!(default_generator().starts_with(concat!("gitlog2rss ", env!("CARGO_PKG_VERSION"))));
+
+        let gen_conf = |generator: &str| yaml(&format!("channel-title: T\n\
+                                                       channel-link: https://example.org/\n\
+                                                       channel-description: D\n\
+                                                       generator: {}\n", generator));
+        let chan = build_channel(&gen_conf("'%n %v'"), &[]).unwrap();
+        assert_eq!(chan.generator(), Some(concat!("gitlog2rss ", env!("CARGO_PKG_VERSION"))));
+        assert_eq!(build_channel(&gen_conf("~"), &[]).unwrap().generator(), None);
+    }
+
+    #[test]
+    fn channel_categories() {
+        let chan_conf = |categories: &str| yaml(&format!("channel-title: T\n\
+                                                          channel-link: https://example.org/\n\
+                                                          channel-description: D\n\
+                                                          channel-categories: {}\n", categories));
+        let conf = chan_conf("[Technik, {name: Mathematik, domain: 'https://jo-so.de/maxima/'}]");
+        let chan = build_channel(&conf, &[]).unwrap();
+        assert_eq!(chan.categories(), [
+            Category { name: "Technik".into(), domain: None },
+            Category { name: "Mathematik".into(), domain: Some("https://jo-so.de/maxima/".into()) },
+        ]);
+
+        assert!(build_channel(&chan_conf("[{domain: x}]"), &[]).is_err());
+        assert!(build_channel(&chan_conf("Technik"), &[]).is_err());
+    }
+
+    #[test]
+    fn channel_image() {
+        let conf = yaml("channel-title: T\n\
+                         channel-link: https://example.org/\n\
+                         channel-description: D\n\
+                         channel-image: {url: 'https://example.org/logo.png', width: 200}\n");
+        let chan = build_channel(&conf, &[]).unwrap();
+        let image = chan.image().unwrap();
+        assert_eq!((image.title(), image.link(), image.width()), ("T", "https://example.org/", Some("200")));
+        assert_eq!(image.height(), None);
+
+        let base_url = url::Url::parse("https://example.org/").unwrap();
+        assert!(check_strict(&conf, &[], &base_url).is_err());
+
+        let conf = yaml("channel-title: T\n\
+                         channel-link: https://example.org/\n\
+                         channel-description: D\n\
+                         channel-image: {width: 88}\n");
+        assert!(build_channel(&conf, &[]).is_err());
+    }
+
+    #[test]
+    fn obfuscated_emails() {
+        let repo = test_repo("obfuscated_emails");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("obfuscate-email".into()), Yaml::Boolean(true));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let rss = String::from_utf8_lossy(&rendered[0].data);
+        assert!(rss.contains("<author>ann at example dot org (Ann Author)</author>"), "{}", rss);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("obfuscate-email".into()), Yaml::String("drop".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let rss = String::from_utf8_lossy(&rendered[0].data);
+        assert!(!rss.contains("<author>") && rss.contains("<dc:creator>Ann Author</dc:creator>"), "{}", rss);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("obfuscate-email".into()), Yaml::String("yes".into()));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn dc_creators() {
+        let repo = test_repo("dc_creators");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("dc-creator".into()), Yaml::Boolean(true));
+            map.insert(Yaml::String("outputs".into()), yaml("[{format: rss}, {format: atom}]"));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let rss = String::from_utf8_lossy(&rendered[0].data);
+        assert!(rss.contains("<dc:creator>"), "{}", rss);
+        assert!(!rss.contains("<author>"), "{}", rss);
+        let atom = String::from_utf8_lossy(&rendered[1].data);
+        assert!(atom.contains("<author>"), "{}", atom);
+        assert!(!atom.contains("<email>"), "{}", atom);
+    }
+
+    #[test]
+    fn websub_hubs() {
+        let repo = test_repo("websub_hubs");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("websub-hub".into()), yaml("[https://hub.example.org/, https://hub.example.com/]"));
+            map.insert(Yaml::String("outputs".into()), yaml("[{format: rss}, {format: atom}, {format: jsonfeed}]"));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let rss = String::from_utf8_lossy(&rendered[0].data);
+        assert!(rss.contains("<atom:link href=\"https://hub.example.org/\" rel=\"hub\"/>"), "{}", rss);
+        let atom = String::from_utf8_lossy(&rendered[1].data);
+        assert!(atom.contains("<link href=\"https://hub.example.com/\" rel=\"hub\"/>"), "{}", atom);
+        let json = String::from_utf8_lossy(&rendered[2].data);
+        assert!(json.contains("\"hubs\":[{\"type\":\"WebSub\",\"url\":\"https://hub.example.org/\"},"), "{}", json);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("websub-hub".into()), Yaml::Integer(1));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn skip_lists() {
+        assert_eq!(skip_hours(&yaml("[0, 23, 7]")).unwrap(), ["0", "23", "7"]);
+        assert!(skip_hours(&Yaml::BadValue).unwrap().is_empty());
+        assert!(skip_hours(&yaml("[24]")).is_err());
+        assert!(skip_hours(&yaml("[1, 1]")).is_err());
+        assert!(skip_hours(&yaml("3")).is_err());
+
+        assert_eq!(skip_days(&yaml("[saturday, Sunday]")).unwrap(), ["Saturday", "Sunday"]);
+        assert!(skip_days(&yaml("[Sonntag]")).is_err());
+        assert!(skip_days(&yaml("[Monday, MONDAY]")).is_err());
+    }
+
+    fn test_entry(link: &str, guid: Option<&str>) -> Entry {
+        let mut item = rss::Item::default();
+        item.set_link(link.to_string());
+        item.set_guid(guid.map(|x| Guid { value: x.to_string(), permalink: false }));
+        Entry {
+            time: git2::Time::new(0, 0),
+            commit: Oid::zero(),
+            status: Delta::Modified,
+            path: String::new(),
+            page: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            author_uri: None,
+            item,
+        }
+    }
+
+    #[test]
+    fn duplicates() {
+        let items = || vec![
+            test_entry("https://example.org/a", None),
+            test_entry("https://example.org/a", None),
+            test_entry("https://example.org/b", Some("x")),
+            test_entry("https://example.org/c", Some("x")),
+        ];
+
+        let mut list = items();
+        resolve_duplicates(&mut list, "warn").unwrap();
+        assert_eq!(list.len(), 4);
+
+        let mut list = items();
+        resolve_duplicates(&mut list, "suffix").unwrap();
+        assert_eq!(list.iter().map(|x| x.item.link().unwrap()).collect::<Vec<_>>(),
+                   ["https://example.org/a", "https://example.org/a#2",
+                    "https://example.org/b", "https://example.org/c"]);
+        assert_eq!(list[3].item.guid().unwrap().value(), "x#2");
+
+        let mut list = items();
+        resolve_duplicates(&mut list, "merge").unwrap();
+        assert_eq!(list.iter().map(|x| x.item.link().unwrap()).collect::<Vec<_>>(),
+                   ["https://example.org/a", "https://example.org/c"]);
+
+        assert!(resolve_duplicates(&mut items(), "drop").is_err());
+    }
+
+    #[test]
+    fn strict_links() {
+        let conf = yaml("channel-title: T\nchannel-link: https://example.org/\nchannel-description: D\n");
+        let base_url = url::Url::parse("https://example.org/").unwrap();
+
+        let mut entry = test_entry("https://example.org/blog/a.html", None);
+        entry.item.set_title("A".to_string());
+        assert!(check_strict(&conf, &[entry], &base_url).is_ok());
+
+        let entry = test_entry("https://example.org/blog/a.html", None);
+        assert!(check_strict(&conf, &[entry], &base_url).is_err());
+
+        let mut entry = test_entry("https://github.com/jo-so/website/pull/1", None);
+        entry.item.set_title("PR".to_string());
+        assert!(check_strict(&conf, &[entry], &base_url).is_ok());
+
+        let mut entry = test_entry("blog/a.html", None);
+        entry.item.set_title("Relative".to_string());
+        assert!(check_strict(&conf, &[entry], &base_url).is_err());
+
+        // the page of a file must be on the site, even if the link is elsewhere
+        let mut entry = test_entry("https://github.com/jo-so/website/pull/1", None);
+        entry.item.set_title("PR".to_string());
+        entry.page = Some("https://example.com/blog/a.html".to_string());
+        assert!(check_strict(&conf, &[entry.clone()], &base_url).is_err());
+        entry.page = Some("https://example.org/blog/a.html".to_string());
+        assert!(check_strict(&conf, &[entry], &base_url).is_ok());
+
+        let conf = yaml("channel-title: T\nchannel-link: https://example.com/\nchannel-description: D\n");
+        assert!(check_strict(&conf, &[], &base_url).is_err());
+
+        assert!(check_strict(&yaml("channel-title: T"), &[], &base_url).is_err());
+    }
+
+    /// Creates a repository with one commit of src/blog/a.md
+    fn test_repo(name: &str) -> std::path::PathBuf {
+        let (dir, repo) = empty_repo(name);
+        fs::create_dir_all(dir.join("src/blog")).unwrap();
+        fs::write(dir.join("src/blog/a.md"), "# A\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/blog/a.md")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Ann Author", "ann@example.org").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Add a", &tree, &[]).unwrap();
+        dir
+    }
+
+    fn test_conf(repo: &Path) -> Yaml {
+        yaml(&format!("repo: {}\n\
+                       base-url: https://example.org/\n\
+                       strip-prefix: src/\n\
+                       item-title-page-new: New /%p\n\
+                       channel-title: T\n\
+                       channel-link: https://example.org/\n\
+                       channel-description: D\n", repo.display()))
+    }
+
+    fn test_params(path: &str) -> Params {
+        Params {
+            paths: vec![path.to_string()],
+            prefix: None,
+            strict: false,
+            hook: false,
+            pretty: None,
+            filter: None,
+            limit: None,
+            since: None,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn outputs() {
+        let repo = test_repo("outputs");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("outputs".into()),
+                       yaml("[{format: rss}, {format: atom}, {format: jsonfeed, file: '-'}]"));
+        }
+
+        let mut params = test_params("src/**");
+        params.pretty = Some((b'\t', 1));
+        let rendered = generate(&conf, &params).unwrap();
+        let texts: Vec<_> = rendered.iter().map(|x| String::from_utf8_lossy(&x.data)).collect();
+        assert_eq!(rendered.iter().map(|x| x.output.format).collect::<Vec<_>>(),
+                   [output::Format::Rss, output::Format::Atom, output::Format::JsonFeed]);
+        assert!(rendered.iter().all(|x| x.output.file.is_none()));
+        assert!(texts[0].contains("<title>New /blog/a.html</title>"), "{}", texts[0]);
+        assert!(texts[1].contains("\n\t<entry>"), "{}", texts[1]);
+        assert!(texts[2].contains("\n\t\"items\": ["), "{}", texts[2]);
+        assert!(texts[2].contains("\"title\": \"New /blog/a.html\""), "{}", texts[2]);
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn empty_feed_is_rendered() {
+        let repo = test_repo("empty");
+        let conf = test_conf(&repo);
+
+        let rendered = generate(&conf, &test_params("nothing/**")).unwrap();
+        assert_eq!(rendered.len(), 1);
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<channel>"), "{}", text);
+        assert!(!text.contains("<item>"), "{}", text);
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        assert!(String::from_utf8_lossy(&rendered[0].data).contains("<item>"));
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn truncated_html() {
+        assert_eq!(truncate_html("<p>Hallo <b>Welt</b></p>", 8), "<p>Hallo <b>We…</b></p>");
+        assert_eq!(truncate_html("<p>Kurz</p>", 4), "<p>Kurz</p>");
+        assert_eq!(truncate_html("a &amp; b", 3), "a &amp;…");
+        assert_eq!(truncate_html("<p>x<br>y<img src=a.png/>z</p>", 2), "<p>x<br>y<img src=a.png/>…</p>");
+        assert_eq!(truncate_html("e\u{301}e\u{301}", 1), "e\u{301}…");
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slugify("blog/Meine Reise (2024).html"), "blog/meine-reise-2024.html");
+        assert_eq!(slugify("/Über uns/Team.html"), "/über-uns/team.html");
+        assert_eq!(slugify("a/Dir"), "a/dir");
+        assert_eq!(slugify("Blog/"), "blog/");
+    }
+
+    #[test]
+    fn slug_links() {
+        let repo = test_repo("slug_links");
+        test_commit(&repo, &[("src/Reisen/Meine Reise (2024).md", Some("# Reise\n"))], "Add Reise");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("slugify".into()), Yaml::Boolean(true));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>New /reisen/meine-reise-2024.html</title>\
+                               <link>https://example.org/reisen/meine-reise-2024.html</link>"), "{}", text);
+    }
+
+    #[test]
+    fn url_paths() {
+        assert_eq!(encode_path("blog/über uns#1?.html"), "blog/%C3%BCber%20uns%231%3F.html");
+        assert_eq!(encode_path("a/b.html"), "a/b.html");
+
+        assert_eq!(decode_utf8("https://example.org/%C3%BCber%20uns.html"), "https://example.org/über%20uns.html");
+        assert_eq!(decode_utf8("https://example.org/%23%2"), "https://example.org/%23%2");
+        // no valid UTF-8
+        assert_eq!(decode_utf8("https://example.org/%FC"), "https://example.org/%FC");
+    }
+
+    #[test]
+    fn url_encoding() {
+        let repo = test_repo("url_encoding");
+        test_commit(&repo, &[("src/blog/über uns.md", Some("# Über uns\n"))], "Add über uns");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("outputs".into()), yaml("[{format: rss}, {format: sitemap}]"));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<link>https://example.org/blog/%C3%BCber%20uns.html</link>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("url-encoding".into()), Yaml::String("utf-8".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<link>https://example.org/blog/über%20uns.html</link>"), "{}", text);
+        let sitemap = String::from_utf8_lossy(&rendered[1].data);
+        assert!(sitemap.contains("<loc>https://example.org/blog/%C3%BCber%20uns.html</loc>"), "{}", sitemap);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("url-encoding".into()), Yaml::String("latin1".into()));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn split_entries_keeps_empty_group() {
+        let groups = split_entries(Vec::new(), None, "");
+        assert_eq!(groups.len(), 1);
+        assert!(groups[&None].is_empty());
+        assert!(split_entries(Vec::new(), Some("section"), "").is_empty());
+    }
+
+    #[test]
+    fn sections() {
+        let entries = ["src/blog/a.md", "src/index.md", "src/blog/b.md"].iter()
+            .map(|path| Entry { path: path.to_string(), ..test_entry("", None) })
+            .collect();
+        let groups = split_entries(entries, Some("section"), "src/");
+        assert_eq!(groups.iter().map(|(key, group)| (key.as_deref().unwrap(), group.len())).collect::<Vec<_>>(),
+                   [("blog", 2), ("root", 1)]);
+
+        let entry = Entry { author_name: "Jörg Sommer".into(), ..test_entry("", None) };
+        assert!(split_entries(vec![entry], Some("author"), "").contains_key(&Some("jörg-sommer".to_string())));
+    }
+
+    #[test]
+    fn path_categories() {
+        assert_eq!(path_category("src/blog/2024/post.md", "src/", 1), Some("blog"));
+        assert_eq!(path_category("src/blog/2024/post.md", "src/", 2), Some("blog/2024"));
+        assert_eq!(path_category("src/blog/2024/post.md", "src/", 5), Some("blog/2024"));
+        assert_eq!(path_category("src/index.md", "src/", 1), None);
+        assert_eq!(path_category("src/blog/post.md", "src/", 0), None);
+    }
+
+    #[test]
+    fn forge_comments() {
+        let repo = test_repo("forge_comments");
+        let head = Repository::open(&repo).unwrap().head().unwrap().peel_to_commit().unwrap().id();
+        let cache = repo.join("forge.json");
+        fs::write(&cache, format!(
+            "{{\"comments\": {{\"{0}\": {{\"count\": 2, \"reactions\": 1, \"url\": \"https://example.org/c?a=1&b=2\", \"time\": {1}}}}}, \
+              \"pulls\": {{\"{0}\": {{\"url\": \"https://github.com/jo-so/website/pull/7\", \"title\": \"Blog <a>\", \
+                           \"description\": \"Mehr & mehr\", \"time\": {1}}}}}}}",
+            head, chrono::Utc::now().timestamp()
+        )).unwrap();
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("forge".into()), yaml(&format!(
+                "{{type: github, repo: jo-so/website, cache-file: '{}', pull-requests: both, \
+                 comments-text: '%n Kommentare & %r Reaktion'}}",
+                cache.display()
+            )));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<comments>https://example.org/c?a=1&amp;b=2</comments>"), "{}", text);
+        assert!(text.contains("<p>Mehr &amp; mehr</p>\n<p><a href=\"https://example.org/c?a=1&amp;b=2\">\
+                               2 Kommentare &amp; 1 Reaktion</a></p>"), "{}", text);
+        assert!(text.contains("<title>Blog &lt;a&gt;</title><link>https://github.com/jo-so/website/pull/7</link>"),
+                "{}", text);
+    }
+
+    #[test]
+    fn placeholders() {
+        let vars = [
+            ("h", "abc1234".to_string()),
+            ("H", "abc1234def".to_string()),
+            ("a", "Ann".to_string()),
+            ("an", "Ann Author".to_string()),
+            ("ad", "2024-03-05T10:00:00+01:00".to_string()),
+        ];
+        assert_eq!(expand("/compare/%h...%H", &vars), "/compare/abc1234...abc1234def");
+        assert_eq!(expand("%an, %a", &vars), "Ann Author, Ann");
+        assert_eq!(expand("100%% %x %", &vars), "100% %x %");
+        assert_eq!(expand("%{ad:%d.%m.%Y}", &vars), "05.03.2024");
+        // no date
+        assert_eq!(expand("%{an:%Y}", &vars), "%{an:%Y}");
+    }
+
+    #[test]
+    fn extensions() {
+        let map: HashMap<_, _> = [("md", "html"), ("scss", "css")].iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(map_extension("blog/a.md", &map), "blog/a.html");
+        assert_eq!(map_extension("style/site.min.scss", &map), "style/site.min.css");
+        assert_eq!(map_extension("blog/pic.png", &map), "blog/pic.png");
+        assert_eq!(map_extension("v1.md/README", &map), "v1.md/README");
+        assert_eq!(map_extension("blog/.md", &map), "blog/.md");
+    }
+
+    #[test]
+    fn root_index_path() {
+        let vars = [("p", "/".to_string())];
+        assert_eq!(expand(&root_template("Neue Seite /%p"), &vars), "Neue Seite /");
+        assert_eq!(expand(&root_template("Seite %p"), &vars), "Seite /");
+    }
+
+    #[test]
+    fn directory_index() {
+        let repo = test_repo("directory_index");
+        test_commit(&repo, &[("src/index.md", Some("# Start\n")), ("src/blog/README.md", Some("# Blog\n")),
+                             ("src/docs/_index.md", Some("# Docs\n"))], "Add index");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("directory-index".into()), Yaml::Boolean(true));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>New /</title><link>https://example.org/</link>"), "{}", text);
+        assert!(text.contains("<title>New /blog/</title><link>https://example.org/blog/</link>"), "{}", text);
+        assert!(text.contains("<link>https://example.org/docs/_index.html</link>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("directory-index".into()), yaml("[_index]"));
+            map.insert(Yaml::String("item-title-page-new".into()), Yaml::String("Neu: %p".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Neu: index.html</title><link>https://example.org/index.html</link>"), "{}", text);
+        assert!(text.contains("<title>Neu: docs/</title><link>https://example.org/docs/</link>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("directory-index".into()), Yaml::Boolean(true));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Neu: /</title><link>https://example.org/</link>"), "{}", text);
+    }
+
+    #[test]
+    fn url_rewrites() {
+        let repo = test_repo("url_rewrites");
+        test_commit(&repo, &[("src/blog/2024-01-15-urlaub.md", Some("# Urlaub\n"))], "Add Urlaub");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("url-rewrites".into()),
+                       yaml("- pattern: '^src/blog/(\\d{4})-(\\d{2})-\\d{2}-(?P<name>.+)\\.md$'\n  \
+                             replacement: '/$1/$2/${name}/'"));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<link>https://example.org/2024/01/urlaub/</link>"), "{}", text);
+        assert!(text.contains("<title>New /2024/01/urlaub/</title>"), "{}", text);
+        assert!(text.contains("<link>https://example.org/blog/a.html</link>"), "{}", text);
+
+        for rules in ["[src]", "- pattern: src", "- {pattern: '(', replacement: x}"] {
+            if let Yaml::Hash(ref mut map) = conf {
+                map.insert(Yaml::String("url-rewrites".into()), yaml(rules));
+            }
+            assert!(generate(&conf, &test_params("src/**")).is_err(), "{}", rules);
+        }
+    }
+
+    #[test]
+    fn extension_map_links() {
+        let repo = test_repo("extension_map_links");
+        test_commit(&repo, &[("src/docs/b.rst", Some("B\n=\n"))], "Add b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("extension-map".into()), yaml("{rst: html}"));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<link>https://example.org/docs/b.html</link>"), "{}", text);
+        assert!(text.contains("<link>https://example.org/blog/a.md</link>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("extension-map".into()), yaml("[rst]"));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn compare_links() {
+        let repo = test_repo("compare_links");
+        {
+            let git = Repository::open(&repo).unwrap();
+            fs::write(repo.join("src/blog/a.md"), "# A\n\nMehr\n").unwrap();
+            let mut index = git.index().unwrap();
+            index.add_path(Path::new("src/blog/a.md")).unwrap();
+            let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = git.head().unwrap().peel_to_commit().unwrap();
+            let sig = git2::Signature::now("Ann Author", "ann@example.org").unwrap();
+            git.commit(Some("HEAD"), &sig, &sig, "Change a", &tree, &[&parent]).unwrap();
+        }
+        let git = Repository::open(&repo).unwrap();
+        let head = git.head().unwrap().peel_to_commit().unwrap();
+        let url = format!("https://example.org/compare/{}...{}", head.parent_id(0).unwrap(), head.id());
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-modified".into()), Yaml::String("Update /%p".into()));
+            map.insert(Yaml::String("compare-url".into()), Yaml::String("https://example.org/compare/%P...%H".into()));
+            map.insert(Yaml::String("compare-text".into()), Yaml::String("Änderungen".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains(&format!("<p><a href=\"{}\">Änderungen</a></p>", url)), "{}", text);
+        // the first commit has no parent to compare with
+        assert_eq!(text.matches("Änderungen").count(), 1, "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("compare-link".into()), Yaml::String("comments".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains(&format!("<comments>{}</comments>", url)), "{}", text);
+        assert!(!text.contains("Änderungen"), "{}", text);
+    }
+
+    /// Commits the files to the worktree, `None` removes a file
+    fn test_commit(dir: &Path, files: &[(&str, Option<&str>)], message: &str) -> Oid {
+        let repo = Repository::open(dir).unwrap();
+        let mut index = repo.index().unwrap();
+        for (path, text) in files {
+            match text {
+                Some(text) => {
+                    fs::create_dir_all(dir.join(path).parent().unwrap()).unwrap();
+                    fs::write(dir.join(path), text).unwrap();
+                    index.add_path(Path::new(path)).unwrap();
+                }
+                None => {
+                    fs::remove_file(dir.join(path)).unwrap();
+                    index.remove_path(Path::new(path)).unwrap();
+                }
+            }
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        // a minute after the parent for a defined order of the walk by time
+        let time = git2::Time::new(parent.time().seconds() + 60, 0);
+        let sig = git2::Signature::new("Ann Author", "ann@example.org", &time).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&parent]).unwrap()
+    }
+
+    #[test]
+    fn last_modified() {
+        let repo = test_repo("last_modified");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b");
+        test_commit(&repo, &[("src/blog/a.md", Some("# A\n\nMehr\n"))], "Change a");
+        test_commit(&repo, &[("src/blog/b.md", None)], "Remove b");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("mode".into()), Yaml::String("last-modified".into()));
+            map.insert(Yaml::String("item-title-page-modified".into()), Yaml::String("Update /%p".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert_eq!(text.matches("<item>").count(), 1, "{}", text);
+        assert!(text.contains("<title>Update /blog/a.html</title>"), "{}", text);
+    }
+
+    #[test]
+    fn new_pages() {
+        let repo = test_repo("new_pages");
+        test_commit(&repo, &[("src/blog/a.md", Some("# A\n\nMehr\n"))], "Change a");
+        test_commit(&repo, &[("src/blog/a.md", None), ("src/blog/b.md", Some("# A\n\nMehr\n"))], "Rename a");
+        test_commit(&repo, &[("src/blog/c.md", Some("# C\n"))], "Add c");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("mode".into()), Yaml::String("new-pages".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        let titles: Vec<_> = text.match_indices("<title>New").map(|(i, _)| &text[i + 7..i + 23]).collect();
+        assert_eq!(titles, ["New /blog/a.html", "New /blog/c.html"], "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("mode".into()), Yaml::String("changes".into()));
+            map.insert(Yaml::String("detect-renames".into()), Yaml::Boolean(true));
+            map.insert(Yaml::String("item-title-page-renamed".into()), Yaml::String("Moved /%p".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Moved /blog/b.html</title>"), "{}", text);
+    }
+
+    #[test]
+    fn deleted_links() {
+        let repo = test_repo("deleted_links");
+        test_commit(&repo, &[("src/blog/a.md", None)], "Remove a");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-removed".into()), Yaml::String("Gone /%p".into()));
+            map.insert(Yaml::String("archive-url".into()), Yaml::String("https://web.archive.org/web/%u".into()));
+            map.insert(Yaml::String("outputs".into()), yaml("[{format: rss}, {format: jsonfeed}]"));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Gone /blog/a.html</title>\
+                               <link>https://web.archive.org/web/https://example.org/blog/a.html</link>"),
+                "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("deleted-link".into()), Yaml::String("none".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Gone /blog/a.html</title><author>"), "{}", text);
+
+        let feed: serde_json::Value = serde_json::from_slice(&rendered[1].data).unwrap();
+        let item = &feed["items"][1];
+        assert_eq!(item["title"], "Gone /blog/a.html");
+        assert!(item["url"].is_null());
+        assert!(item["id"].as_str().unwrap().starts_with("urn:sha1:"));
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.remove(&Yaml::String("archive-url".into()));
+            map.insert(Yaml::String("deleted-link".into()), Yaml::String("archive".into()));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn follow_renames() {
+        let repo = test_repo("follow_renames");
+        test_commit(&repo, &[("src/blog/a.md", Some("# A\n\nMehr\n"))], "Change a");
+        test_commit(&repo, &[("src/blog/a.md", None), ("src/blog/b.md", Some("# A\n\nMehr\n"))], "Rename a");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("follow-renames".into()), Yaml::Boolean(true));
+            map.insert(Yaml::String("max-item-age".into()), Yaml::String("1 year".into()));
+        }
+
+        // a commit of a side branch older than max-item-age doesn't stop the walk
+        {
+            let git = Repository::open(&repo).unwrap();
+            let head = git.head().unwrap().peel_to_commit().unwrap();
+            let first = head.parent(0).unwrap().parent(0).unwrap();
+            let old = git2::Signature::new("Ann Author", "ann@example.org", &git2::Time::new(1_000_000_000, 0)).unwrap();
+            let side = git.commit(None, &old, &old, "Old", &first.tree().unwrap(), &[&first]).unwrap();
+            let side = git.find_commit(side).unwrap();
+            let time = git2::Time::new(head.time().seconds() + 60, 0);
+            let sig = git2::Signature::new("Ann Author", "ann@example.org", &time).unwrap();
+            git.commit(Some("HEAD"), &sig, &sig, "Merge", &head.tree().unwrap(), &[&head, &side]).unwrap();
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>New /blog/a.html</title><link>https://example.org/blog/b.html</link>"),
+                "{}", text);
+    }
+
+    #[test]
+    fn content_guids() {
+        let guids = |name: &str, message: &str, guid: &str| {
+            let repo = test_repo(name);
+            test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], message);
+            let mut conf = test_conf(&repo);
+            if let Yaml::Hash(ref mut map) = conf {
+                map.insert(Yaml::String("guid".into()), Yaml::String(guid.into()));
+            }
+            let rendered = generate(&conf, &test_params("src/**")).unwrap();
+            let text = String::from_utf8_lossy(&rendered[0].data).into_owned();
+            text.split("<guid isPermaLink=\"false\">").skip(1)
+                .map(|x| x[..40].to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // a rewritten history with other commits gives the same GUIDs
+        let a = guids("content_guids_a", "Add b", "content");
+        assert_eq!(a.len(), 2);
+        assert_eq!(a, guids("content_guids_b", "Add the page b", "content"));
+        assert_ne!(guids("content_guids_c", "Add b", "opaque"), guids("content_guids_d", "Add the page b", "opaque"));
+    }
+
+    #[test]
+    fn uuid_guids() {
+        let repo = test_repo("uuid_guids");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("guid".into()), Yaml::String("uuid".into()));
+            map.insert(Yaml::String("outputs".into()), yaml("[{format: rss}, {format: atom}]"));
+        }
+
+        let git = Repository::open(&repo).unwrap();
+        let commit = git.head().unwrap().peel_to_commit().unwrap().id();
+        let namespace = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, b"https://example.org/");
+        let id = uuid::Uuid::new_v5(&namespace, format!("{}:src/blog/a.md", commit).as_bytes()).urn().to_string();
+        assert_eq!(id.len(), "urn:uuid:".len() + 36);
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let rss = String::from_utf8_lossy(&rendered[0].data);
+        assert!(rss.contains(&format!("<guid isPermaLink=\"false\">{}</guid>", id)), "{}", rss);
+        let atom = String::from_utf8_lossy(&rendered[1].data);
+        assert!(atom.contains(&format!("<id>{}</id>", id)), "{}", atom);
+
+        // the aliases
+        for (alias, guid) in [("url", "<guid>https://example.org/blog/a.html</guid>"),
+                              ("commit-path", "<guid isPermaLink=\"false\">")] {
+            if let Yaml::Hash(ref mut map) = conf {
+                map.insert(Yaml::String("guid".into()), Yaml::String(alias.into()));
+            }
+            let rendered = generate(&conf, &test_params("src/**")).unwrap();
+            assert!(String::from_utf8_lossy(&rendered[0].data).contains(guid), "{}", alias);
+        }
+    }
+
+    #[test]
+    fn batches() {
+        let repo = test_repo("batches");
+        test_commit(&repo, &[("src/b.md", Some("# B\n")), ("src/c & d.md", Some("# C\n"))], "Add b, c");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("batch-threshold".into()), Yaml::Integer(1));
+            map.insert(Yaml::String("item-title-batch".into()), Yaml::String("%n Seiten".into()));
+            map.insert(Yaml::String("guid".into()), Yaml::String("opaque".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert_eq!(text.matches("<item>").count(), 2, "{}", text);
+        assert!(text.contains("<title>2 Seiten</title><link>https://example.org/</link><description><![CDATA[<ul>\
+                               <li><a href=\"https://example.org/b.html\">New /b.html</a></li>\
+                               <li><a href=\"https://example.org/c%20&amp;%20d.html\">New /c &amp; d.html</a></li>\
+                               </ul>]]></description>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("mode".into()), Yaml::String("last-modified".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        assert_eq!(String::from_utf8_lossy(&rendered[0].data).matches("<item>").count(), 3);
+    }
+
+    #[test]
+    fn several_refs() {
+        let repo = test_repo("several_refs");
+        let git = Repository::open(&repo).unwrap();
+        let main = git.head().unwrap().shorthand().unwrap().to_string();
+        git.branch("stable", &git.head().unwrap().peel_to_commit().unwrap(), false).unwrap();
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b");
+
+        // the same change on stable and a page only there
+        git.set_head("refs/heads/stable").unwrap();
+        git.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b (cherry picked)");
+        test_commit(&repo, &[("src/blog/c.md", Some("# C\n"))], "Add c");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("refs".into()), yaml(&format!("[{}, stable]", main)));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert_eq!(text.matches("<title>New /blog/b.html</title>").count(), 1, "{}", text);
+        assert_eq!(text.matches("<item>").count(), 3, "{}", text);
+
+        // the current files come from all refs, not only from HEAD
+        git.set_head(&format!("refs/heads/{}", main)).unwrap();
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("mode".into()), Yaml::String("last-modified".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>New /blog/c.html</title>"), "{}", text);
+        assert_eq!(text.matches("<item>").count(), 3, "{}", text);
+    }
+
+    #[test]
+    fn original_commits() {
+        assert_eq!(original_commit("Fix\n\n(cherry picked from commit 0123abc)\n"), Some("0123abc"));
+        assert_eq!(original_commit("Fix\n\nX-Original-Commit: 4567def\n"), Some("4567def"));
+        assert_eq!(original_commit("Fix\n\n(cherry picked from commit xyz)\n"), None);
+        assert_eq!(original_commit("Fix the cherry picked from commit handling"), None);
+    }
+
+    #[test]
+    fn backports() {
+        let repo = test_repo("backports");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))],
+                    "Add b\n\n(cherry picked from commit 0123456789abcdef0123456789abcdef01234567)\n");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("commit-url".into()), Yaml::String("https://example.org/c/%H?x&y".into()));
+            map.insert(Yaml::String("backport-text".into()), Yaml::String("Rückportierung von %h".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>New /blog/b.html</title><link>https://example.org/blog/b.html</link>\
+                               <description><![CDATA[<p><a href=\"https://example.org/c/\
+                               0123456789abcdef0123456789abcdef01234567?x&amp;y\">Rückportierung von 0123456</a></p>]]>\
+                               </description>"), "{}", text);
+        assert_eq!(text.matches("<category>backport</category>").count(), 1, "{}", text);
+    }
+
+    #[test]
+    fn ref_categories() {
+        let repo = test_repo("ref_categories");
+        let git = Repository::open(&repo).unwrap();
+        let main = git.head().unwrap().shorthand().unwrap().to_string();
+        git.branch("stable", &git.head().unwrap().peel_to_commit().unwrap(), false).unwrap();
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b");
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("refs".into()), yaml(&format!("[{}, 'refs/heads/st*']", main)));
+            map.insert(Yaml::String("item-title-page-new".into()), Yaml::String("New /%p (%b)".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains(&format!("<title>New /blog/a.html ({}, stable)</title>", main)), "{}", text);
+        assert!(text.contains(&format!("<title>New /blog/b.html ({})</title>", main)), "{}", text);
+        assert_eq!(text.matches("<category>stable</category>").count(), 1, "{}", text);
+        assert_eq!(text.matches(&format!("<category>{}</category>", main)).count(), 2, "{}", text);
+    }
+
+    #[test]
+    fn ttl_auto() {
+        let at = |minutes: &[i64]| minutes.iter()
+            .map(|m| {
+                let mut e = test_entry("https://example.org/a", None);
+                e.time = git2::Time::new(m * 60, 0);
+                e
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(auto_ttl(&at(&[0, 100, 400, 500])), 100);
+        assert_eq!(auto_ttl(&at(&[0, 10, 20])), 60);
+        assert_eq!(auto_ttl(&at(&[0, 100_000])), 7 * 24 * 60);
+        assert_eq!(auto_ttl(&at(&[0])), 7 * 24 * 60);
+        // changes in the same second count once
+        assert_eq!(auto_ttl(&at(&[0, 0, 0, 120])), 120);
+    }
+
+    #[test]
+    fn contributors() {
+        let repo = test_repo("contributors");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b");
+        {
+            let git = Repository::open(&repo).unwrap();
+            let head = git.head().unwrap().peel_to_commit().unwrap();
+            let time = git2::Time::new(head.time().seconds() + 60, 0);
+            let sig = git2::Signature::new("Bea & Bot", "bea@example.org", &time).unwrap();
+            git.commit(Some("HEAD"), &sig, &sig, "Fix b & c", &head.tree().unwrap(), &[&head]).unwrap();
+            let time = git2::Time::new(head.time().seconds() + 120, 0);
+            let sig = git2::Signature::new("Bea & Bot", "bea@example.org", &time).unwrap();
+            fs::write(repo.join("src/blog/b.md"), "# B\n\nMehr\n").unwrap();
+            let mut index = git.index().unwrap();
+            index.add_path(Path::new("src/blog/b.md")).unwrap();
+            let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = git.head().unwrap().peel_to_commit().unwrap();
+            git.commit(Some("HEAD"), &sig, &sig, "Change b", &tree, &[&parent]).unwrap();
+        }
+
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("mode".into()), Yaml::String("contributors".into()));
+            map.insert(Yaml::String("item-title-first-contribution".into()), Yaml::String("Erster Beitrag von %an".into()));
+            map.insert(Yaml::String("commit-url".into()), Yaml::String("https://example.org/c/%H".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        // the commit without changes doesn't count
+        assert_eq!(text.matches("<item>").count(), 2, "{}", text);
+        assert!(text.contains("<title>Erster Beitrag von Ann Author</title>"), "{}", text);
+        assert!(text.contains("<title>Erster Beitrag von Bea &amp; Bot</title>"), "{}", text);
+        assert!(text.contains("<description><![CDATA[<p>Change b</p>]]></description>"), "{}", text);
+    }
+
+    #[test]
+    fn hook_updates_of_refs() {
+        let (a, b) = ("1".repeat(40), "2".repeat(40));
+        let zero = "0".repeat(40);
+        let input = format!("{a} {b} refs/heads/main\n\
+                             {a} {b} refs/heads/wip\n\
+                             {zero} {b} refs/tags/v1\n\
+                             {a} {zero} refs/heads/old\n");
+
+        let updates = hook_updates(input.as_bytes(), &Yaml::BadValue, "refs/heads/main").unwrap();
+        assert_eq!(updates.iter().map(|x| (x.name.as_str(), x.old.to_string())).collect::<Vec<_>>(),
+                   [("main", a.clone())]);
+
+        let refs = yaml("[main, 'v*', old]");
+        let updates = hook_updates(input.as_bytes(), &refs, "refs/heads/main").unwrap();
+        assert_eq!(updates.iter().map(|x| x.name.as_str()).collect::<Vec<_>>(), ["main", "v1"]);
+        assert!(updates[1].old.is_zero());
+
+        assert!(hook_updates("bogus\n".as_bytes(), &refs, "HEAD").is_err());
+    }
+
+    #[test]
+    fn request_params() {
+        let repo = test_repo("request_params");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n")), ("src/notes/c.md", Some("# C\n"))], "Add b and c");
+        test_commit(&repo, &[("src/blog/d.md", Some("# D\n"))], "Add d");
+        let conf = test_conf(&repo);
+
+        let titles = |params: &Params| {
+            let rendered = generate(&conf, params).unwrap();
+            let text = String::from_utf8_lossy(&rendered[0].data).into_owned();
+            regex::Regex::new("<title>New (/[^<]+)</title>").unwrap().captures_iter(&text)
+                .map(|x| x[1].to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut params = test_params("src/**");
+        assert_eq!(titles(&params).len(), 4);
+        params.filter = Some("blog/**".to_string());
+        assert_eq!(titles(&params), ["/blog/a.html", "/blog/b.html", "/blog/d.html"]);
+        // the newest items
+        params.limit = Some(2);
+        assert_eq!(titles(&params), ["/blog/b.html", "/blog/d.html"]);
+
+        params.format = Some(output::Format::Atom);
+        let rendered = generate(&conf, &params).unwrap();
+        assert!(String::from_utf8_lossy(&rendered[0].data).contains("<feed xmlns=\"http://www.w3.org/2005/Atom\""));
+    }
+
+    #[test]
+    fn html_page() {
+        let repo = test_repo("html_page");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B & C\n"))], "Add b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("outputs".into()),
+                       yaml("[{format: html, url: 'https://example.org/rss'}]"));
+            map.insert(Yaml::String("language".into()), Yaml::String("de".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        assert_eq!(output::content_type(&rendered[0].output), "text/html; charset=utf-8");
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.starts_with("<!DOCTYPE html>\n<html lang=\"de\">"), "{}", text);
+        assert!(text.contains("<link rel=\"alternate\" type=\"application/rss+xml\" href=\"https://example.org/rss\">"), "{}", text);
+        // the newest change first
+        let b = text.find("<a href=\"https://example.org/blog/b.html\">New /blog/b.html</a>").unwrap();
+        let a = text.find("<a href=\"https://example.org/blog/a.html\">New /blog/a.html</a>").unwrap();
+        assert!(b < a, "{}", text);
+        assert!(text.contains("<span class=\"author\">Ann Author</span>"), "{}", text);
+    }
+
+    #[test]
+    fn markdown_document() {
+        let repo = test_repo("markdown_document");
+        test_commit(&repo, &[("src/blog/b_c.md", Some("# B\n"))], "Add b");
+        let conf = test_conf(&repo);
+        let mut params = test_params("src/**");
+        params.format = Some(output::Format::Markdown);
+
+        let rendered = generate(&conf, &params).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        let git = Repository::open(&repo).unwrap();
+        let day = datetime(&git.head().unwrap().peel_to_commit().unwrap().time()).date_naive();
+        assert!(text.starts_with(&format!("# T\n\nD\n\n## {}\n\n\
+                                           - [New /blog/b\\_c.html](<https://example.org/blog/b_c.html>) (Ann Author)\n",
+                                          day)), "{}", text);
+        assert!(text.ends_with("\n- [New /blog/a.html](<https://example.org/blog/a.html>) (Ann Author)\n"), "{}", text);
+    }
+
+    #[test]
+    fn sitemap() {
+        let repo = test_repo("sitemap");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b");
+        test_commit(&repo, &[("src/blog/a.md", Some("# A2\n"))], "Change a");
+        test_commit(&repo, &[("src/blog/b.md", None)], "Remove b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("xml-standalone".into()), Yaml::Boolean(true));
+        }
+        let mut params = test_params("src/**");
+        params.format = Some(output::Format::Sitemap);
+
+        let rendered = generate(&conf, &params).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        let git = Repository::open(&repo).unwrap();
+        let change = git.head().unwrap().peel_to_commit().unwrap().parent(0).unwrap();
+        // the last change of a and no removed page
+        assert_eq!(text, format!("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n\
+                                  <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  \
+                                  <url><loc>https://example.org/blog/a.html</loc><lastmod>{}</lastmod></url>\n\
+                                  </urlset>\n", datetime(&change.time()).to_rfc3339()));
+    }
+
+    #[test]
+    fn opml_list() {
+        let repo = test_repo("opml_list");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("outputs".into()),
+                       yaml("[{format: rss, file: '-', url: 'https://example.org/rss'},
+                              {format: atom, file: '-'},
+                              {format: html, file: '-', url: 'https://example.org/changes.html'}]"));
+            map.insert(Yaml::String("opml-output".into()), Yaml::String("feeds.opml".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let opml = rendered.last().unwrap();
+        assert_eq!(opml.output.file.as_deref(), Some("feeds.opml"));
+        assert_eq!(output::content_type(&opml.output), "text/x-opml");
+        // only the feeds with a URL
+        assert_eq!(String::from_utf8_lossy(&opml.data),
+                   "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  \
+                    <head><title>T</title></head>\n  <body>\n    \
+                    <outline type=\"rss\" text=\"T\" title=\"T\" xmlUrl=\"https://example.org/rss\" htmlUrl=\"https://example.org/\"/>\n  \
+                    </body>\n</opml>\n");
+    }
+
+    #[test]
+    fn h_feed() {
+        let repo = test_repo("h_feed");
+        let conf = test_conf(&repo);
+        let mut params = test_params("src/**");
+        params.format = Some(output::Format::HFeed);
+
+        let rendered = generate(&conf, &params).unwrap();
+        assert_eq!(output::content_type(&rendered[0].output), "text/html; charset=utf-8");
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.starts_with("<div class=\"h-feed\">\n  \
+                                  <h2 class=\"p-name\"><a class=\"u-url\" href=\"https://example.org/\">T</a></h2>"), "{}", text);
+        assert!(text.contains("<article class=\"h-entry\">\n    \
+                               <h3 class=\"p-name\"><a class=\"u-url\" href=\"https://example.org/blog/a.html\">New /blog/a.html</a></h3>"), "{}", text);
+        assert!(text.contains("<span class=\"p-author h-card\"><a class=\"p-name u-email\" href=\"mailto:"), "{}", text);
+    }
+
+    #[test]
+    fn archive_pages() {
+        let repo = test_repo("archive_pages");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b");
+        test_commit(&repo, &[("src/blog/c.md", Some("# C\n"))], "Add c");
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-archive-{}", std::process::id()));
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("outputs".into()),
+                       yaml(&format!("[{{format: atom, file: '{}/feed.xml', url: 'https://example.org/feed.xml'}}]",
+                                     dir.display())));
+            map.insert(Yaml::String("page-size".into()), Yaml::Integer(2));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let files: Vec<_> = rendered.iter().map(|x| x.output.file.clone().unwrap()).collect();
+        assert_eq!(files, [format!("{}/feed-1.xml", dir.display()), format!("{}/feed.xml", dir.display())]);
+        let archive = String::from_utf8_lossy(&rendered[0].data);
+        assert!(archive.contains("<fh:archive></fh:archive>"), "{}", archive);
+        assert!(archive.contains("New /blog/b.html") && !archive.contains("New /blog/c.html"), "{}", archive);
+        let current = String::from_utf8_lossy(&rendered[1].data);
+        assert!(current.contains("<link href=\"https://example.org/feed-1.xml\" rel=\"prev-archive\"/>"), "{}", current);
+
+        let mut params = test_params("src/**");
+        params.hook = true;
+        assert!(generate(&conf, &params).is_err());
+    }
+
+    #[test]
+    fn item_descriptions() {
+        assert_eq!(message_html("Fix <b>\n\nMore text\nand more\n\nSigned-off-by: A <a@example.org>\n\
+                                 (cherry picked from commit 0123abc)\n"),
+                   "<p>Fix &lt;b&gt;</p>\n<p>More text\nand more</p>");
+        // the subject is kept
+        assert_eq!(message_html("Fixes: #1\n"), "<p>Fixes: #1</p>");
+        assert_eq!(message_html("Subject\n\nNote: this is text\nnot a trailer\n"),
+                   "<p>Subject</p>\n<p>Note: this is text\nnot a trailer</p>");
+
+        let repo = test_repo("item_descriptions");
+        test_commit(&repo, &[("src/blog/b&c.md", Some("# B\n"))], "Add b\n\nWith details\n\nSigned-off-by: A <a@b>");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-description".into()), Yaml::String("commit-message".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<description><![CDATA[<p>Add b</p>\n<p>With details</p>]]></description>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-description".into()), Yaml::String("Seite <em>/%p</em>".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<description><![CDATA[Seite <em>/blog/b&amp;c.html</em>]]></description>"), "{}", text);
+    }
+
+    #[test]
+    fn item_content() {
+        let repo = test_repo("item_content");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n\n[A](a.html)\n"))], "Add b");
+        test_commit(&repo, &[("src/blog/b.md", None)], "Remove b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-content".into()), Yaml::Boolean(true));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        // the removed page has no content
+        assert_eq!(text.matches("<content:encoded>").count(), 2, "{}", text);
+        assert!(text.contains("<content:encoded><![CDATA[<h1>B</h1>\n\
+                               <p><a href=\"https://example.org/blog/a.html\">A</a></p>\n]]></content:encoded>"), "{}", text);
+    }
+
+    #[test]
+    fn html_diffs() {
+        let repo = test_repo("html_diffs");
+        test_commit(&repo, &[("src/blog/a.md", Some("# A\n\nx < y\nz\n"))], "Change a");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-modified".into()), Yaml::String("Changed /%p".into()));
+            map.insert(Yaml::String("item-diff".into()), Yaml::String("html".into()));
+            map.insert(Yaml::String("item-diff-max-lines".into()), Yaml::Integer(2));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        // only the modified page has a diff
+        assert_eq!(text.matches("<pre class=\"diff\">").count(), 1, "{}", text);
+        assert!(text.contains("<pre class=\"diff\">@@ -1 +1,4 @@\n # A\n\
+                               <ins style=\"background:#e6ffec;text-decoration:none\">+</ins>\n…\n</pre>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-diff".into()), Yaml::String("words".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<p># A<ins style=\"background:#e6ffec;text-decoration:none\"> x &lt; y z</ins></p>"),
+                "{}", text);
+    }
+
+    #[test]
+    fn diffstats() {
+        let repo = test_repo("diffstats");
+        let id = test_commit(&repo, &[("src/blog/a.md", Some("# A1\n\nText\n"))], "Change a");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-modified".into()), Yaml::String("/%p (+%+/-%-) in %h".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains(&format!("<title>/blog/a.html (+3/-1) in {}</title>", &id.to_string()[..7])), "{}", text);
+        // the title of new pages has no diffstat
+        assert!(text.contains("<title>New /blog/a.html</title>"), "{}", text);
+    }
+
+    #[test]
+    fn commit_placeholders() {
+        let repo = test_repo("commit_placeholders");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add <b>\n\nWith details");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-new".into()), Yaml::String("%s: /%p von %an".into()));
+            map.insert(Yaml::String("item-description".into()), Yaml::String("<q>%s</q> %ae".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Add &lt;b&gt;: /blog/b.html von Ann Author</title>"), "{}", text);
+        assert!(text.contains("<description><![CDATA[<q>Add &lt;b&gt;</q> ann@example.org]]></description>"), "{}", text);
+    }
+
+    #[test]
+    fn item_categories() {
+        let repo = test_repo("item_categories");
+        test_commit(&repo, &[("src/index.md", Some("# Start\n"))], "Add index");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("category-depth".into()), Yaml::Integer(1));
+            map.insert(Yaml::String("categories".into()),
+                       yaml("{'src/blog/**': blog, 'src/*.md': {name: Start, domain: 'https://example.org/'}}"));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        // the same category from the directory and the map only once
+        assert_eq!(text.matches("<category>blog</category>").count(), 1, "{}", text);
+        assert!(text.contains("<category domain=\"https://example.org/\">Start</category>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("categories".into()), yaml("{'src/**': {domain: x}}"));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn front_matter_categories() {
+        let repo = test_repo("front_matter_categories");
+        test_commit(&repo, &[("src/blog/b.md", Some("---\ntags: [rust, git]\ncategories: blog, rust\n---\n# B\n"))],
+                    "Add b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("front-matter-categories".into()), Yaml::Boolean(true));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<category>rust</category><category>git</category><category>blog</category><pubDate>"),
+                "{}", text);
+        assert_eq!(text.matches("<category>").count(), 3, "{}", text);
+    }
+
+    #[test]
+    fn media_enclosures() {
+        let repo = test_repo("media_enclosures");
+        test_commit(&repo, &[("src/blog/a.png", Some("png")), ("src/blog/a.txt", Some("text"))], "Add files");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("media-enclosures".into()), Yaml::Boolean(true));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<enclosure url=\"https://example.org/blog/a.png\" length=\"3\" type=\"image/png\"/>"), "{}", text);
+        assert_eq!(text.matches("<enclosure ").count(), 1, "{}", text);
+    }
+
+    #[test]
+    fn media_thumbnails() {
+        let repo = test_repo("media_thumbnails");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n\n![Bild](img/b.jpg)\n"))], "Add b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-thumbnail".into()), Yaml::Boolean(true));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<media:thumbnail url=\"https://example.org/blog/img/b.jpg\"></media:thumbnail>"), "{}", text);
+        assert!(text.contains("<media:content medium=\"image\" type=\"image/jpeg\" url=\"https://example.org/blog/img/b.jpg\"></media:content>"), "{}", text);
+        assert_eq!(text.matches("<media:content ").count(), 1, "{}", text);
+    }
+
+    #[test]
+    fn podcast() {
+        let repo = test_repo("podcast");
+        test_commit(&repo, &[("src/podcast/folge-1.mp3", Some("mp3"))], "Add episode");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("media-enclosures".into()), Yaml::Boolean(true));
+            map.insert(Yaml::String("podcast".into()),
+                       yaml("{author: Ann, episodes: {src/podcast/folge-1.mp3: {duration: '1:02'}}}"));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("xmlns:itunes="), "{}", text);
+        assert!(text.contains("<itunes:author>Ann</itunes:author>"), "{}", text);
+        assert!(text.contains("<itunes:duration>1:02</itunes:duration>"), "{}", text);
+    }
+
+    #[test]
+    fn georss_points() {
+        let repo = test_repo("georss_points");
+        test_commit(&repo, &[("src/blog/b.md", Some("---\nlocation: 52.52, 13.40\n---\n# B\n"))], "Add b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("front-matter-georss".into()), Yaml::Boolean(true));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("xmlns:georss=\"http://www.georss.org/georss\""), "{}", text);
+        assert!(text.contains("<georss:point>52.52 13.4</georss:point>"), "{}", text);
+        assert_eq!(text.matches("<georss:point>").count(), 1, "{}", text);
+    }
+
+    #[test]
+    fn comments_urls() {
+        let repo = test_repo("comments_urls");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-comments-url".into()),
+                       Yaml::String("https://example.org/diskussion/%p?c=%h".into()));
+        }
+
+        let head = Repository::open(&repo).unwrap().head().unwrap().peel_to_commit().unwrap().id();
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains(&format!("<comments>https://example.org/diskussion/blog/a.html?c={}</comments>",
+                                       &head.to_string()[..7])), "{}", text);
+    }
+
+    #[test]
+    fn item_sources() {
+        let repo = test_repo("item_sources");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-source".into()), Yaml::String("https://example.org/feed.rss".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<source url=\"https://example.org/feed.rss\">T</source>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-source".into()), yaml("{url: https://example.org/a.rss, title: A}"));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<source url=\"https://example.org/a.rss\">A</source>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-source".into()), yaml("{title: A}"));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn item_templates() {
+        let repo = test_repo("item_templates");
+        test_commit(&repo, &[("src/blog/a.md", Some("# A\n\nneu\n"))], "Change a");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("authors".into()), yaml("{ann@example.org: Ann}"));
+            map.insert(Yaml::String("item-title-template".into()), Yaml::String(
+                "{% if status == \"added\" %}Neu{% else %}Geändert{% endif %}: {{ path }} \
+                 ({{ stats.added }}+/{{ stats.removed }}-)".into()));
+            map.insert(Yaml::String("item-description-template".into()),
+                       Yaml::String("<p>{{ commit.summary }} von {{ author.name }}</p>".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Neu: src/blog/a.md (1+/0-)</title>"), "{}", text);
+        assert!(text.contains("<title>Geändert: src/blog/a.md (2+/0-)</title>"), "{}", text);
+        assert!(text.contains("<description><![CDATA[<p>Change a von Ann</p>]]></description>"), "{}", text);
+    }
+
+    #[test]
+    fn title_overrides() {
+        let repo = test_repo("title_overrides");
+        test_commit(&repo, &[("src/news/n.md", Some("# N\n")), ("src/docs/d.md", Some("# D\n"))], "Add news");
+        test_commit(&repo, &[("src/news/n.md", Some("# N\n\nmehr\n")), ("src/docs/d.md", Some("# D\n\nmehr\n"))],
+                    "Change news");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-modified".into()), Yaml::String("Changed /%p".into()));
+            map.insert(Yaml::String("title-overrides".into()), yaml(
+                "[{pathspec: 'src/news/**', added: 'Neuigkeit /%p', modified: 'Neuigkeit /%p (+%+)'}, \
+                  {pathspec: 'src/**', added: 'Neu /%p'}]"));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Neuigkeit /news/n.html</title>"), "{}", text);
+        assert!(text.contains("<title>Neuigkeit /news/n.html (+2)</title>"), "{}", text);
+        assert!(text.contains("<title>Neu /docs/d.html</title>"), "{}", text);
+        assert!(text.contains("<title>Neu /blog/a.html</title>"), "{}", text);
+        assert!(text.contains("<title>Changed /docs/d.html</title>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("title-overrides".into()), yaml("[{added: 'Neu /%p'}]"));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn extension_titles() {
+        let repo = test_repo("extension_titles");
+        test_commit(&repo, &[("src/blog/b.JPG", Some("jpg"))], "Add photo");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-new.jpg".into()), Yaml::String("Neues Foto /%p".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Neues Foto /blog/b.JPG</title>"), "{}", text);
+        assert!(text.contains("<title>New /blog/a.html</title>"), "{}", text);
+    }
+
+    #[test]
+    fn fallback_titles() {
+        let repo = test_repo("fallback_titles");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n"))], "Add b");
+        test_commit(&repo, &[("src/blog/b.md", Some("# B\n\nmehr\n"))], " ");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-page-new".into()), Yaml::String(" ".into()));
+        }
+
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Add b</title>"), "{}", text);
+        assert!(text.contains("<title>blog/b.html</title>"), "{}", text);
+    }
+
+    #[test]
+    fn front_matter_titles() {
+        let repo = test_repo("front_matter_titles");
+        test_commit(&repo, &[("src/blog/b.md", Some("---\ntitle: ' Mein Urlaub '\n---\n# B\n"))], "Add b");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title".into()), Yaml::String("front-matter".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Mein Urlaub</title>"), "{}", text);
+        assert!(text.contains("<title>New /blog/a.html</title>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.remove(&Yaml::String("item-title".into()));
+            map.insert(Yaml::String("item-title-page-new".into()), Yaml::String("Neu: %t".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Neu: Mein Urlaub</title>"), "{}", text);
+        assert!(text.contains("<title>Neu: blog/a.html</title>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title-template".into()),
+                       Yaml::String("{% if title %}{{ title }}{% else %}{{ path }}{% endif %}".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Mein Urlaub</title>"), "{}", text);
+        assert!(text.contains("<title>src/blog/a.md</title>"), "{}", text);
+    }
+
+    #[test]
+    fn localized_titles() {
+        let repo = test_repo("localized_titles");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.remove(&Yaml::String("item-title-page-new".into()));
+            map.insert(Yaml::String("locale".into()), Yaml::String("de-DE".into()));
+            map.insert(Yaml::String("item-title-page-new.md".into()), Yaml::String("%{date:%A} /%p".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        let day = chrono::Local::now().format_localized("%A", chrono::Locale::de_DE).to_string();
+        assert!(text.contains(&format!("<title>{} /blog/a.html</title>", day)), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.remove(&Yaml::String("item-title-page-new.md".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Neue Seite /blog/a.html</title>"), "{}", text);
+    }
+
+    #[test]
+    fn subject_titles() {
+        let repo = test_repo("subject_titles");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title".into()), Yaml::String("commit-subject".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Add a</title>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title".into()), Yaml::String("commit-subject-path".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<title>Add a: /blog/a.html</title>"), "{}", text);
+
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("item-title".into()), Yaml::String("subject".into()));
+        }
+        assert!(generate(&conf, &test_params("src/**")).is_err());
+    }
+
+    #[test]
+    fn command_descriptions() {
+        let env = [("FILE_PATH", "src/a.md".to_string()), ("STATUS", "added".to_string())];
+        let timeout = Duration::from_secs(10);
+        let dir = Path::new("/");
+        assert_eq!(command_description("echo \"<p>$STATUS $FILE_PATH</p>\"", dir, &env, timeout).unwrap().as_deref(),
+                   Some("<p>added src/a.md</p>"));
+        assert_eq!(command_description("echo x; exit 1", dir, &env, timeout).unwrap(), None);
+        assert_eq!(command_description("true", dir, &env, timeout).unwrap(), None);
+        // PATH is kept for the programs
+        assert!(command_description("git --version", dir, &env, timeout).unwrap().is_some());
+
+        let start = Instant::now();
+        assert_eq!(command_description("echo x; sleep 5", dir, &env, Duration::from_millis(100)).unwrap(), None);
+        assert!(start.elapsed() < Duration::from_secs(4));
+
+        let repo = test_repo("command_descriptions");
+        let mut conf = test_conf(&repo);
+        if let Yaml::Hash(ref mut map) = conf {
+            map.insert(Yaml::String("description-command".into()),
+                       Yaml::String("git show \"$COMMIT:$FILE_PATH\" | sed 's/^# \\(.*\\)/<h1>\\1<\\/h1>/'".into()));
+        }
+        let rendered = generate(&conf, &test_params("src/**")).unwrap();
+        let text = String::from_utf8_lossy(&rendered[0].data);
+        assert!(text.contains("<description><![CDATA[<h1>A</h1>]]></description>"), "{}", text);
+    }
 }
@@ -0,0 +1,74 @@
+//! Helpers to extract information from Markdown sources
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Returns the source of the first image of a Markdown text; this is either
+/// an image like `![alt](src "title")` or an HTML tag `<img src="…">`
+pub fn first_image(text: &str) -> Option<&str> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(concat!(
+        r#"!\[[^\]]*\]\(\s*<?([^)\s>]+)>?(?:\s+["'(][^)]*)?\)"#,
+        r#"|<img\s[^>]*?src\s*=\s*["']([^"']+)["']"#,
+    )).unwrap());
+
+    re.captures(text)
+        .and_then(|cap| cap.get(1).or_else(|| cap.get(2)))
+        .map(|m| m.as_str())
+}
+
+/// Renders the Markdown text, without a leading YAML front matter, as HTML;
+/// relative links and images are resolved against `base`, the URL of the page
+pub fn to_html(text: &str, base: &url::Url) -> String {
+    use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+    let text = crate::frontmatter::split(text).1;
+
+    let resolve = |url: CowStr<'static>| -> CowStr<'static> {
+        match base.join(&url) {
+            Ok(abs) if url::Url::parse(&url).is_err() && !url.starts_with('#') => abs.to_string().into(),
+            _ => url,
+        }
+    };
+
+    let parser = Parser::new_ext(text, Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES
+                                 | Options::ENABLE_STRIKETHROUGH)
+        .map(|event| match event {
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+                link_type, dest_url: resolve(dest_url.into_static()), title, id
+            }),
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+                link_type, dest_url: resolve(dest_url.into_static()), title, id
+            }),
+            event => event,
+        });
+
+    let mut out = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut out, parser);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_images() {
+        assert_eq!(first_image("Text\n\n![Bild](img/a.png \"Titel\")\n![b](b.png)"), Some("img/a.png"));
+        assert_eq!(first_image("![Bild](<a.png>)"), Some("a.png"));
+        assert_eq!(first_image("<p><img alt=x src='/c.jpg'></p>"), Some("/c.jpg"));
+        assert_eq!(first_image("[Link](a.png)"), None);
+    }
+
+    #[test]
+    fn html() {
+        let base = url::Url::parse("https://example.org/blog/a.html").unwrap();
+        let text = "---\ntags: [a]\n---\n# A\n\n[B](b.html) [C](/c.html) [D](https://d.org/) [E](#e)\n\n![Bild](img/x.png)\n";
+        assert_eq!(to_html(text, &base),
+                   "<h1>A</h1>\n<p><a href=\"https://example.org/blog/b.html\">B</a> \
+                    <a href=\"https://example.org/c.html\">C</a> <a href=\"https://d.org/\">D</a> \
+                    <a href=\"#e\">E</a></p>\n\
+                    <p><img src=\"https://example.org/blog/img/x.png\" alt=\"Bild\" /></p>\n");
+        assert_eq!(to_html("a ~~b~~", &base), "<p>a <del>b</del></p>\n");
+    }
+}
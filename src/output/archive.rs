@@ -0,0 +1,156 @@
+//! Splitting a feed into the current feed and archive pages, see
+//! https://www.rfc-editor.org/rfc/rfc5005#section-4
+
+use super::{expand_path, Output};
+use std::{collections::BTreeMap, error, ops::Range};
+
+const NAMESPACE: &str = "http://purl.org/syndication/history/1.0";
+
+/// The current feed or an archive page of an output
+pub struct Page {
+    /// the output with the file and URL of the page
+    pub output: Output,
+    /// the range of the items in the page
+    pub range: Range<usize>,
+    /// relation and URL template of the links to the other pages
+    links: Vec<(&'static str, String)>,
+    archive: bool,
+}
+
+/// Returns the name with the number of the page before the extension of the
+/// last path component
+fn page_name(name: &str, number: usize) -> String {
+    let start = name.rfind('/').map_or(0, |x| x + 1);
+    match name[start..].rfind('.') {
+        Some(dot) if dot > 0 => format!("{}-{}{}", &name[..start + dot], number, &name[start + dot..]),
+        _ => format!("{}-{}", name, number),
+    }
+}
+
+/// Returns the pages of the output for `len` items; the archive pages have
+/// `size` items and are numbered from the oldest, so they don't change, once
+/// they are complete; the current feed has the remaining 1 to `size` items
+pub fn pages(output: &Output, len: usize, size: usize) -> Result<Vec<Page>, Box<dyn error::Error>> {
+    let (Some(file), Some(url)) = (&output.file, &output.url) else {
+        return Err("With page-size, each rss and atom output needs a file and url".into());
+    };
+    let archives = len.saturating_sub(1) / size;
+
+    let mut pages = Vec::with_capacity(archives + 1);
+    for number in 1..=archives {
+        let mut links = vec![("current", url.clone())];
+        if number > 1 {
+            links.push(("prev-archive", page_name(url, number - 1)));
+        }
+        if number < archives {
+            links.push(("next-archive", page_name(url, number + 1)));
+        }
+        pages.push(Page {
+            output: Output {
+                file: Some(page_name(file, number)),
+                url: Some(page_name(url, number)),
+                ..output.clone()
+            },
+            range: (number - 1) * size..number * size,
+            links,
+            archive: true,
+        });
+    }
+
+    pages.push(Page {
+        output: output.clone(),
+        range: archives * size..len,
+        links: if archives > 0 { vec![("prev-archive", page_name(url, archives))] } else { Vec::new() },
+        archive: false,
+    });
+
+    Ok(pages)
+}
+
+impl Page {
+    /// Adds the links to the other pages and, for archive pages, the element
+    /// `fh:archive` to the channel
+    pub fn add_links(&self, chan: &mut rss::Channel, vars: &BTreeMap<&str, String>)
+                     -> Result<(), Box<dyn error::Error>>
+    {
+        let atom = chan.atom_ext.get_or_insert_with(Default::default);
+        for (rel, url) in &self.links {
+            atom.links.push(rss::extension::atom::Link {
+                href: expand_path(url, vars)?,
+                rel: rel.to_string(),
+                ..Default::default()
+            });
+        }
+
+        if self.archive {
+            chan.namespaces.insert("fh".into(), NAMESPACE.into());
+            chan.extensions.entry("fh".into()).or_default().insert("archive".into(), vec![
+                rss::extension::Extension { name: "fh:archive".into(), ..Default::default() }
+            ]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the links and the element `fh:archive` of the channel for the
+/// Atom feed
+pub fn atom_links(chan: &rss::Channel, feed: &mut atom_syndication::Feed) {
+    for link in chan.atom_ext.iter().flat_map(|x| x.links()) {
+        feed.links.push(atom_syndication::Link {
+            href: link.href().to_string(),
+            rel: link.rel().to_string(),
+            ..Default::default()
+        });
+    }
+
+    if chan.extensions.get("fh").is_some_and(|x| x.contains_key("archive")) {
+        feed.namespaces.insert("fh".into(), NAMESPACE.into());
+        feed.extensions.entry("fh".into()).or_default().insert("archive".into(), vec![
+            atom_syndication::extension::Extension { name: "fh:archive".into(), ..Default::default() }
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Format;
+
+    #[test]
+    fn archive_pages() {
+        assert_eq!(page_name("www/feed.xml", 2), "www/feed-2.xml");
+        assert_eq!(page_name("www.d/feed", 1), "www.d/feed-1");
+        assert_eq!(page_name("www/.feed", 1), "www/.feed-1");
+
+        let output = Output {
+            format: Format::Rss,
+            file: Some("www/feed.xml".into()),
+            url: Some("https://example.org/{lang}/feed.xml".into()),
+            template: None,
+        };
+        let pages = pages(&output, 7, 3).unwrap();
+        assert_eq!(pages.iter().map(|x| x.range.clone()).collect::<Vec<_>>(), [0..3, 3..6, 6..7]);
+        assert_eq!(pages[1].output.file.as_deref(), Some("www/feed-2.xml"));
+        assert_eq!(pages[2].output.file.as_deref(), Some("www/feed.xml"));
+
+        let vars = [("lang", "de".to_string())].into();
+        let mut chan = rss::Channel::default();
+        pages[0].add_links(&mut chan, &vars).unwrap();
+        let links: Vec<_> = chan.atom_ext.as_ref().unwrap().links().iter()
+            .map(|x| (x.rel(), x.href()))
+            .collect();
+        assert_eq!(links, [("current", "https://example.org/de/feed.xml"),
+                           ("next-archive", "https://example.org/de/feed-2.xml")]);
+        assert!(chan.extensions["fh"].contains_key("archive"));
+
+        let mut chan = rss::Channel::default();
+        pages[2].add_links(&mut chan, &vars).unwrap();
+        assert_eq!(chan.atom_ext.as_ref().unwrap().links()[0].href(), "https://example.org/de/feed-2.xml");
+        assert!(chan.extensions.is_empty());
+
+        // a full current feed
+        assert_eq!(super::pages(&output, 6, 3).unwrap().last().unwrap().range, 3..6);
+        assert!(super::pages(&Output { url: None, ..output }, 6, 3).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversion of the channel to an Atom feed
+
+use super::entry_id;
+use crate::{datetime, Entry};
+use atom_syndication::{
+    Category,
+    Content,
+    Feed,
+    Generator,
+    Link,
+    Person,
+    Text,
+};
+
+pub fn feed(chan: &rss::Channel, entries: &[Entry]) -> Feed {
+    let updated = entries.iter()
+        .map(|e| datetime(&e.time))
+        .max()
+        .unwrap_or_else(|| chrono::Utc::now().into());
+
+    Feed {
+        title: Text::plain(chan.title()),
+        id: chan.link().to_string(),
+        updated,
+        generator: chan.generator().map(|x| Generator { value: x.to_string(), ..Default::default() }),
+        links: vec![Link { href: chan.link().to_string(), ..Default::default() }],
+        rights: chan.copyright().map(Text::plain),
+        subtitle: Some(Text::html(chan.description())),
+        lang: chan.language().map(str::to_string),
+        categories: chan.categories().iter().map(category).collect(),
+        logo: chan.image().map(|x| x.url().to_string()),
+        entries: entries.iter().map(entry).collect(),
+        ..Default::default()
+    }
+}
+
+fn category(cat: &rss::Category) -> Category {
+    Category {
+        term: cat.name().to_string(),
+        scheme: cat.domain().map(str::to_string),
+        label: None,
+    }
+}
+
+fn entry(e: &Entry) -> atom_syndication::Entry {
+    let item = &e.item;
+    let date = datetime(&e.time);
+
+    let mut links: Vec<_> = item.link()
+        .map(|link| Link { href: link.to_string(), ..Default::default() })
+        .into_iter()
+        .collect();
+    if let Some(enc) = item.enclosure() {
+        links.push(Link {
+            href: enc.url().to_string(),
+            rel: "enclosure".into(),
+            mime_type: Some(enc.mime_type().to_string()),
+            length: Some(enc.length().to_string()),
+            ..Default::default()
+        });
+    }
+
+    atom_syndication::Entry {
+        title: Text::plain(item.title().unwrap_or(&e.path)),
+        id: entry_id(e),
+        updated: date,
+        published: Some(date),
+        authors: vec![Person {
+            name: e.author_name.clone(),
+            email: Some(e.author_email.clone()).filter(|x| !x.is_empty()),
+            uri: e.author_uri.clone(),
+        }],
+        categories: item.categories().iter().map(category).collect(),
+        links,
+        summary: item.description().map(Text::html),
+        content: item.content().map(|x| Content {
+            value: Some(x.to_string()),
+            content_type: Some("html".into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,96 @@
+//! iCalendar with an event for each change, see
+//! https://www.rfc-editor.org/rfc/rfc5545
+
+use super::entry_id;
+use crate::{datetime, Entry};
+use chrono::Utc;
+
+/// Escapes the text value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+/// Appends the content line, folded after 75 octets, to `doc`
+fn push_line(doc: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            doc.push_str("\r\n ");
+            len = 1;
+        }
+        doc.push(c);
+        len += c.len_utf8();
+    }
+    doc.push_str("\r\n");
+}
+
+/// Returns the VCALENDAR with a VEVENT at the time of each change
+pub fn document(chan: &rss::Channel, entries: &[Entry]) -> String {
+    let mut doc = String::new();
+    push_line(&mut doc, "BEGIN:VCALENDAR");
+    push_line(&mut doc, "VERSION:2.0");
+    push_line(&mut doc, &format!("PRODID:-//gitlog2rss//{}//EN", env!("CARGO_PKG_VERSION")));
+    push_line(&mut doc, &format!("X-WR-CALNAME:{}", escape(chan.title())));
+
+    for e in entries {
+        let time = datetime(&e.time).with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string();
+        push_line(&mut doc, "BEGIN:VEVENT");
+        push_line(&mut doc, &format!("UID:{}", escape(&entry_id(e))));
+        push_line(&mut doc, &format!("DTSTAMP:{}", time));
+        push_line(&mut doc, &format!("DTSTART:{}", time));
+        push_line(&mut doc, &format!("SUMMARY:{}", escape(e.item.title().unwrap_or_default())));
+        if let Some(link) = e.item.link() {
+            push_line(&mut doc, &format!("DESCRIPTION:{}", escape(link)));
+            push_line(&mut doc, &format!("URL:{}", link));
+        }
+        if !e.author_name.is_empty() && !e.author_email.is_empty() {
+            push_line(&mut doc, &format!("ORGANIZER;CN=\"{}\":mailto:{}",
+                                         e.author_name.replace('"', "'"), e.author_email));
+        }
+        let categories: Vec<_> = e.item.categories().iter().map(|x| escape(x.name())).collect();
+        if !categories.is_empty() {
+            push_line(&mut doc, &format!("CATEGORIES:{}", categories.join(",")));
+        }
+        push_line(&mut doc, "END:VEVENT");
+    }
+
+    push_line(&mut doc, "END:VCALENDAR");
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folded_events() {
+        let mut chan = rss::Channel::default();
+        chan.set_title("Tom, Jerry; Co.");
+        let mut item = rss::Item::default();
+        item.set_title(format!("Neue Seite {}", "ä".repeat(40)));
+        item.set_link("https://example.org/a.html".to_string());
+        let entry = Entry {
+            time: git2::Time::new(86400, 60),
+            commit: git2::Oid::zero(),
+            status: git2::Delta::Added,
+            path: "a.md".to_string(),
+            page: None,
+            author_name: "Ann \"A\" Author".to_string(),
+            author_email: "ann@example.org".to_string(),
+            author_uri: None,
+            item,
+        };
+
+        let doc = document(&chan, &[entry]);
+        assert!(doc.contains("\r\nX-WR-CALNAME:Tom\\, Jerry\\; Co.\r\n"), "{}", doc);
+        assert!(doc.contains("\r\nDTSTART:19700102T000000Z\r\n"), "{}", doc);
+        assert!(doc.contains("\r\nORGANIZER;CN=\"Ann 'A' Author\":mailto:ann@example.org\r\n"), "{}", doc);
+        assert!(doc.lines().all(|x| x.len() <= 76), "{}", doc);
+        assert!(doc.contains("\r\n ä"), "{}", doc);
+        assert!(doc.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"), "{}", doc);
+    }
+}
@@ -0,0 +1,71 @@
+//! Conversion of the channel to a JSON Feed, see https://jsonfeed.org/version/1.1
+
+use super::entry_id;
+use crate::{datetime, Entry};
+use serde_json::{json, Map, Value};
+
+pub fn feed(chan: &rss::Channel, entries: &[Entry]) -> Value {
+    let mut feed = Map::new();
+    feed.insert("version".into(), "https://jsonfeed.org/version/1.1".into());
+    feed.insert("title".into(), chan.title().into());
+    feed.insert("home_page_url".into(), chan.link().into());
+    feed.insert("description".into(), chan.description().into());
+    let hubs: Vec<_> = chan.atom_ext.iter()
+        .flat_map(|x| x.links())
+        .filter(|x| x.rel() == "hub")
+        .map(|x| json!({ "type": "WebSub", "url": x.href() }))
+        .collect();
+    if !hubs.is_empty() {
+        feed.insert("hubs".into(), hubs.into());
+    }
+    if let Some(image) = chan.image() {
+        feed.insert("icon".into(), image.url().into());
+    }
+    if let Some(lang) = chan.language() {
+        feed.insert("language".into(), lang.into());
+    }
+    feed.insert("items".into(), entries.iter().map(item).collect());
+
+    feed.into()
+}
+
+fn item(e: &Entry) -> Value {
+    let item = &e.item;
+    let mut obj = Map::new();
+    obj.insert("id".into(), entry_id(e).into());
+    if let Some(link) = item.link() {
+        obj.insert("url".into(), link.into());
+    }
+    if let Some(title) = item.title() {
+        obj.insert("title".into(), title.into());
+    }
+    // either content_html or content_text is required
+    match item.content().or_else(|| item.description()) {
+        Some(html) => obj.insert("content_html".into(), html.into()),
+        None => obj.insert("content_text".into(), item.title().unwrap_or(&e.path).into()),
+    };
+    if let (Some(_), Some(desc)) = (item.content(), item.description()) {
+        obj.insert("summary".into(), desc.into());
+    }
+    obj.insert("date_published".into(), datetime(&e.time).to_rfc3339().into());
+    obj.insert("authors".into(), match e.author_uri {
+        Some(ref uri) => json!([{ "name": e.author_name, "url": uri }]),
+        None => json!([{ "name": e.author_name }]),
+    });
+
+    if !item.categories().is_empty() {
+        obj.insert("tags".into(), item.categories().iter().map(|x| x.name()).collect());
+    }
+
+    if let Some(enc) = item.enclosure() {
+        let mut att = Map::new();
+        att.insert("url".into(), enc.url().into());
+        att.insert("mime_type".into(), enc.mime_type().into());
+        if let Ok(size) = enc.length().parse::<u64>() {
+            att.insert("size_in_bytes".into(), size.into());
+        }
+        obj.insert("attachments".into(), vec![Value::from(att)].into());
+    }
+
+    obj.into()
+}
@@ -0,0 +1,274 @@
+//! Mail messages (RFC 5322) of the items for an mbox file or a Maildir, see
+//! https://www.rfc-editor.org/rfc/rfc4155 and https://cr.yp.to/proto/maildir.html
+
+use super::entry_id;
+use crate::{datetime, html_escape, Entry};
+use chrono::{FixedOffset, Utc};
+use std::{collections::BTreeMap, error, fmt::Write, fs, path::Path};
+use yaml_rust::Yaml;
+
+/// The settings of the mail outputs from the config
+pub struct Options {
+    from: Option<String>,
+    to: Option<String>,
+    /// one message per day instead of one per item
+    digest: bool,
+}
+
+impl Options {
+    pub fn from_conf(conf: &Yaml) -> Result<Self, Box<dyn error::Error>> {
+        let digest = match conf["mail-digest"].as_str() {
+            None | Some("none") => false,
+            Some("daily") => true,
+            Some(x) => return Err(format!("Invalid value of config entry 'mail-digest': {}", x).into()),
+        };
+
+        Ok(Options {
+            from: conf["mail-from"].as_str().map(str::to_string),
+            to: conf["mail-to"].as_str().map(str::to_string),
+            digest,
+        })
+    }
+}
+
+/// Encodes the header value as RFC 2047 encoded words, if it contains
+/// characters outside of ASCII
+fn encode_header(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return value.to_string();
+    }
+
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in value.chars() {
+        let mut buf = [0; 4];
+        let mut encoded = String::new();
+        for b in c.encode_utf8(&mut buf).bytes() {
+            match b {
+                b' ' => encoded.push('_'),
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'!' | b'*' | b'+' | b'-' | b'/' => {
+                    encoded.push(b as char)
+                }
+                _ => { let _ = write!(encoded, "={:02X}", b); }
+            }
+        }
+        // an encoded word must not be longer than 75 characters
+        if word.len() + encoded.len() > 75 - "=?utf-8?q??=".len() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push_str(&encoded);
+    }
+    words.push(word);
+
+    words.iter().map(|x| format!("=?utf-8?q?{}?=", x)).collect::<Vec<_>>().join("\n ")
+}
+
+/// Encodes the display name of the address `Name <addr>`
+fn encode_address(value: &str) -> String {
+    match value.rfind('<') {
+        Some(pos) if pos > 0 => format!("{} {}", encode_header(value[..pos].trim()), &value[pos..]),
+        _ => value.to_string(),
+    }
+}
+
+/// Appends the message in mboxrd format to `mbox`
+fn push_message(mbox: &mut String, opts: &Options, id: &str, date: &chrono::DateTime<FixedOffset>,
+                subject: &str, body: &str) -> Result<(), Box<dyn error::Error>>
+{
+    let from = opts.from.as_deref().ok_or("The mail outputs need the config entry 'mail-from'")?;
+    let to = opts.to.as_deref().ok_or("The mail outputs need the config entry 'mail-to'")?;
+    let hash = git2::Oid::hash_object(git2::ObjectType::Blob, id.as_bytes())?;
+
+    let _ = writeln!(mbox, "From gitlog2rss {}", date.with_timezone(&Utc).format("%a %b %e %H:%M:%S %Y"));
+    let _ = writeln!(mbox, "From: {}", encode_address(from));
+    let _ = writeln!(mbox, "To: {}", encode_address(to));
+    let _ = writeln!(mbox, "Subject: {}", encode_header(subject));
+    let _ = writeln!(mbox, "Date: {}", date.to_rfc2822());
+    let _ = writeln!(mbox, "Message-ID: <{}@gitlog2rss>", hash);
+    mbox.push_str("MIME-Version: 1.0\n\
+                   Content-Type: text/html; charset=utf-8\n\
+                   Content-Transfer-Encoding: 8bit\n\n");
+
+    for line in body.lines() {
+        if line.trim_start_matches('>').starts_with("From ") {
+            mbox.push('>');
+        }
+        mbox.push_str(line);
+        mbox.push('\n');
+    }
+    mbox.push('\n');
+
+    Ok(())
+}
+
+/// Returns the HTML of the entry for the body of a message
+fn entry_html(e: &Entry) -> String {
+    let title = html_escape(e.item.title().unwrap_or_default());
+    let mut html = match e.item.link() {
+        Some(link) => format!("<h2><a href=\"{}\">{}</a></h2>\n", html_escape(link), title),
+        None => format!("<h2>{}</h2>\n", title),
+    };
+    if let Some(text) = e.item.description() {
+        html.push_str(text);
+        html.push('\n');
+    }
+    html
+}
+
+/// Returns the mbox with one message for each entry or, with the digest, for
+/// each day
+pub fn mbox(chan: &rss::Channel, entries: &[Entry], opts: &Options) -> Result<String, Box<dyn error::Error>> {
+    let mut mbox = String::new();
+
+    if !opts.digest {
+        for e in entries {
+            push_message(&mut mbox, opts, &entry_id(e), &datetime(&e.time),
+                         e.item.title().unwrap_or_default(), &entry_html(e))?;
+        }
+        return Ok(mbox);
+    }
+
+    let mut days = BTreeMap::<_, Vec<&Entry>>::new();
+    for e in entries {
+        days.entry(datetime(&e.time).date_naive()).or_default().push(e);
+    }
+    for (day, group) in days {
+        // the date of a digest is that of its newest change
+        let date = datetime(&group.last().unwrap().time);
+        let body = group.iter().rev().map(|e| entry_html(e)).collect::<String>();
+        let id = format!("{}#{}", chan.link(), day);
+        push_message(&mut mbox, opts, &id, &date, &format!("{} {}", chan.title(), day), &body)?;
+    }
+
+    Ok(mbox)
+}
+
+/// Splits the mboxrd data into the message ID and text of each message
+fn split(mbox: &str) -> Vec<(String, String)> {
+    let mut messages = Vec::new();
+    for text in mbox.split("\nFrom gitlog2rss ").filter(|x| !x.is_empty()) {
+        // skip the envelope line
+        let Some((_, text)) = text.split_once('\n') else { continue };
+        let id = text.lines()
+            .find_map(|x| x.strip_prefix("Message-ID: <"))
+            .and_then(|x| x.split('@').next())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut message = String::with_capacity(text.len());
+        for line in text.trim_end_matches('\n').lines() {
+            let line = match line.strip_prefix('>') {
+                Some(rest) if rest.trim_start_matches('>').starts_with("From ") => rest,
+                _ => line,
+            };
+            message.push_str(line);
+            message.push('\n');
+        }
+        messages.push((id, message));
+    }
+    messages
+}
+
+/// Delivers the messages of the mbox data to the Maildir `dir`, which gets
+/// created; messages already in `new` or `cur` are skipped
+pub fn write_maildir(dir: &str, mbox: &[u8]) -> Result<usize, Box<dyn error::Error>> {
+    let dir = Path::new(dir);
+    for sub in ["tmp", "new", "cur"] {
+        fs::create_dir_all(dir.join(sub))
+            .map_err(|err| format!("Failed to create directory {}: {}", dir.join(sub).display(), err))?;
+    }
+
+    let delivered: Vec<_> = fs::read_dir(dir.join("cur"))?
+        .chain(fs::read_dir(dir.join("new"))?)
+        .filter_map(|x| x.ok())
+        .filter_map(|x| x.file_name().to_str().map(|x| x.split(':').next().unwrap_or(x).to_string()))
+        .collect();
+
+    let mut count = 0;
+    // with the newline before the first separator
+    for (id, message) in split(&format!("\n{}", String::from_utf8_lossy(mbox))) {
+        let name = format!("{}.gitlog2rss", id);
+        if delivered.contains(&name) {
+            continue;
+        }
+        let tmp = dir.join("tmp").join(&name);
+        fs::write(&tmp, message)
+            .map_err(|err| format!("Failed to write {}: {}", tmp.display(), err))?;
+        fs::rename(&tmp, dir.join("new").join(&name))?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers() {
+        assert_eq!(encode_header("New page"), "New page");
+        assert_eq!(encode_header("Grüße"), "=?utf-8?q?Gr=C3=BC=C3=9Fe?=");
+        assert_eq!(encode_header("a\tb"), "=?utf-8?q?a=09b?=");
+
+        let long = encode_header(&"ä".repeat(30));
+        assert!(long.split("\n ").all(|x| x.len() <= 75), "{}", long);
+        assert_eq!(long.matches("=?utf-8?q?").count(), 3);
+
+        assert_eq!(encode_address("Jörg <j@example.org>"), "=?utf-8?q?J=C3=B6rg?= <j@example.org>");
+        assert_eq!(encode_address("<j@example.org>"), "<j@example.org>");
+    }
+
+    fn entry(seconds: i64, title: &str, description: &str) -> Entry {
+        let mut item = rss::Item::default();
+        item.set_title(title.to_string());
+        item.set_link("https://example.org/a.html".to_string());
+        item.set_description(description.to_string());
+        Entry {
+            time: git2::Time::new(seconds, 0),
+            commit: git2::Oid::zero(),
+            status: git2::Delta::Modified,
+            path: format!("{}.md", seconds),
+            page: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            author_uri: None,
+            item,
+        }
+    }
+
+    #[test]
+    fn digest_in_maildir() {
+        let conf = yaml_rust::YamlLoader::load_from_str("mail-from: Jörg <j@example.org>\n\
+                                                         mail-to: list@example.org\n\
+                                                         mail-digest: daily\n").unwrap();
+        let opts = Options::from_conf(&conf[0]).unwrap();
+        let mut chan = rss::Channel::default();
+        chan.set_title("T");
+        chan.set_link("https://example.org/");
+        let entries = [entry(0, "A", "<p>a</p>"), entry(60, "B", "From here\n>From there"),
+                       entry(86400, "C", "")];
+
+        let mbox = mbox(&chan, &entries, &opts).unwrap();
+        assert_eq!(mbox.matches("\nSubject: T 1970-01-0").count(), 2, "{}", mbox);
+        assert!(mbox.starts_with("From gitlog2rss Thu Jan  1 00:01:00 1970\n\
+                                  From: =?utf-8?q?J=C3=B6rg?= <j@example.org>\n\
+                                  To: list@example.org\n\
+                                  Subject: T 1970-01-01\n"), "{}", mbox);
+        // the newest item first and lines with From escaped
+        assert!(mbox.contains("<h2><a href=\"https://example.org/a.html\">B</a></h2>\n>From here\n>>From there\n\
+                               <h2><a href=\"https://example.org/a.html\">A</a></h2>\n"), "{}", mbox);
+
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-maildir-{}", std::process::id()));
+        assert_eq!(write_maildir(dir.to_str().unwrap(), mbox.as_bytes()).unwrap(), 2);
+        // the messages are delivered once
+        assert_eq!(write_maildir(dir.to_str().unwrap(), mbox.as_bytes()).unwrap(), 0);
+        let mut messages: Vec<_> = fs::read_dir(dir.join("new")).unwrap()
+            .map(|x| fs::read_to_string(x.unwrap().path()).unwrap())
+            .collect();
+        messages.sort();
+        assert!(messages[0].starts_with("From: "), "{}", messages[0]);
+        assert!(messages.iter().any(|x| x.contains("\nFrom here\n>From there\n")), "{:?}", messages);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
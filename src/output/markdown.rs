@@ -0,0 +1,53 @@
+//! Conversion of the channel to a Markdown document like CHANGELOG.md
+
+use crate::{datetime, Entry};
+use std::fmt::Write;
+
+/// Escapes the characters with a meaning in inline Markdown
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Returns the items, newest first, grouped by day
+pub fn document(chan: &rss::Channel, entries: &[Entry]) -> String {
+    let mut doc = format!("# {}\n\n{}\n", escape(chan.title()), escape(chan.description()));
+
+    let mut day = None;
+    for e in entries.iter().rev() {
+        let date = datetime(&e.time).date_naive();
+        if day != Some(date) {
+            let _ = write!(doc, "\n## {}\n\n", date);
+            day = Some(date);
+        }
+
+        let title = escape(e.item.title().unwrap_or(&e.path));
+        let _ = match e.item.link() {
+            Some(link) => write!(doc, "- [{}](<{}>)", title, link),
+            None => write!(doc, "- {}", title),
+        };
+        if !e.author_name.is_empty() {
+            let _ = write!(doc, " ({})", escape(&e.author_name));
+        }
+        doc.push('\n');
+    }
+
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_text() {
+        assert_eq!(escape("Use `x*2` for [a]_b_ <c>"), "Use \\`x\\*2\\` for \\[a\\]\\_b\\_ \\<c\\>");
+        assert_eq!(escape("C:\\temp"), "C:\\\\temp");
+    }
+}
@@ -0,0 +1,537 @@
+//! The formats the items can be written in
+
+mod archive;
+mod atom;
+mod ics;
+mod jsonfeed;
+mod mail;
+mod markdown;
+mod ndjson;
+mod opml;
+mod sitemap;
+mod template;
+
+pub use archive::pages;
+pub use mail::Options as MailOptions;
+pub use opml::Feed;
+
+use crate::{xml, Entry};
+use log::info;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    error,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+use yaml_rust::Yaml;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Rss,
+    Atom,
+    JsonFeed,
+    /// a user-supplied Tera template
+    Template,
+    /// an HTML page of the changes
+    Html,
+    /// an HTML fragment with h-feed and h-entry microformats
+    HFeed,
+    /// a Markdown document like CHANGELOG.md
+    Markdown,
+    /// a sitemap.xml with the last change of each page
+    Sitemap,
+    /// one JSON object per line for each item
+    NdJson,
+    /// an iCalendar with an event for each change
+    Ics,
+    /// a file with one mail message for each item
+    Mbox,
+    /// a directory to deliver mail messages of new items to
+    Maildir,
+    /// the list of the feeds of the config entry `opml-output`
+    Opml,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rss" => Some(Format::Rss),
+            "atom" => Some(Format::Atom),
+            "jsonfeed" | "json" => Some(Format::JsonFeed),
+            "template" => Some(Format::Template),
+            "html" => Some(Format::Html),
+            "h-feed" | "hfeed" => Some(Format::HFeed),
+            "markdown" | "md" => Some(Format::Markdown),
+            "sitemap" => Some(Format::Sitemap),
+            "ndjson" => Some(Format::NdJson),
+            "ics" | "ical" => Some(Format::Ics),
+            "mbox" => Some(Format::Mbox),
+            "maildir" => Some(Format::Maildir),
+            _ => None,
+        }
+    }
+}
+
+/// An output file of a channel
+#[derive(Clone, Debug)]
+pub struct Output {
+    pub format: Format,
+    /// `None` for stdout
+    pub file: Option<String>,
+    /// the public URL of the file for `atom:link rel="self"`
+    pub url: Option<String>,
+    /// the template file for the format `template`
+    pub template: Option<String>,
+}
+
+/// Reads the list `outputs` from the config, or the map of format to file;
+/// without it, RSS gets written to stdout
+pub fn from_conf(conf: &Yaml) -> Result<Vec<Output>, Box<dyn error::Error>> {
+    let list = match &conf["outputs"] {
+        Yaml::Array(list) => list,
+        Yaml::Hash(map) => return map.iter()
+            .map(|(name, file)| {
+                let (Some(name), Some(file)) = (name.as_str(), file.as_str()) else {
+                    return Err("Invalid entry in config entry 'outputs'".into());
+                };
+                let format = Format::from_name(name).filter(|x| *x != Format::Template)
+                    .ok_or_else(|| format!("Invalid format in config entry 'outputs': {}", name))?;
+                Ok(Output {
+                    format,
+                    file: Some(file).filter(|x| *x != "-").map(str::to_string),
+                    url: conf["self-url"].as_str().filter(|_| format == Format::Rss).map(str::to_string),
+                    template: None,
+                })
+            })
+            .collect(),
+        Yaml::BadValue => return Ok(vec![Output {
+            format: Format::Rss,
+            file: None,
+            url: conf["self-url"].as_str().map(str::to_string),
+            template: None,
+        }]),
+        _ => return Err("Invalid value of config entry 'outputs': expected a list or map".into()),
+    };
+
+    list.iter()
+        .map(|entry| {
+            let name = entry["format"].as_str().unwrap_or("rss");
+            let format = Format::from_name(name)
+                .ok_or_else(|| format!("Invalid format in config entry 'outputs': {}", name))?;
+            let file = entry["file"].as_str().filter(|x| *x != "-").map(str::to_string);
+            let url = entry["url"].as_str()
+                .or_else(|| conf["self-url"].as_str().filter(|_| format == Format::Rss))
+                .map(str::to_string);
+            let template = entry["template"].as_str().map(str::to_string);
+            if format == Format::Template && template.is_none() {
+                return Err("An output with format template needs the entry 'template'".into());
+            }
+            Ok(Output { format, file, url, template })
+        })
+        .collect()
+}
+
+impl Format {
+    /// Whether readers can subscribe to the output
+    pub fn is_feed(self) -> bool {
+        matches!(self, Format::Rss | Format::Atom | Format::JsonFeed | Format::HFeed)
+    }
+}
+
+/// Returns the output of the config entry `opml-output` with the list of the
+/// feeds
+pub fn opml(conf: &Yaml, feeds: &[Feed]) -> Option<(Output, Vec<u8>)> {
+    let file = conf["opml-output"].as_str()?;
+    let title = conf["opml-title"].as_str().or_else(|| conf["channel-title"].as_str()).unwrap_or_default();
+    let output = Output {
+        format: Format::Opml,
+        file: Some(file).filter(|x| *x != "-").map(str::to_string),
+        url: None,
+        template: None,
+    };
+    Some((output, opml::document(title, feeds).into_bytes()))
+}
+
+/// Returns the MIME type of the format of the output
+pub fn content_type(output: &Output) -> &'static str {
+    match output.format {
+        Format::Rss => "application/rss+xml",
+        Format::Atom => "application/atom+xml",
+        Format::JsonFeed => "application/feed+json",
+        Format::Html | Format::HFeed => "text/html; charset=utf-8",
+        Format::Markdown => "text/markdown; charset=utf-8",
+        Format::Sitemap => "application/xml",
+        Format::NdJson => "application/x-ndjson",
+        Format::Ics => "text/calendar; charset=utf-8",
+        Format::Mbox | Format::Maildir => "application/mbox",
+        Format::Opml => "text/x-opml",
+        Format::Template => crate::mime_type(output.file.as_deref().or(output.template.as_deref()).unwrap_or_default()),
+    }
+}
+
+/// Adds the items of the existing RSS file of the output before the items of
+/// the channel, for `--hook`; the old items older than `min_time` (seconds
+/// since the epoch) are dropped and of all only the newest `limit` ones kept
+pub fn merge_existing(output: &Output, vars: &BTreeMap<&str, String>, chan: &mut rss::Channel,
+                      min_time: Option<i64>, limit: Option<usize>)
+                      -> Result<(), Box<dyn error::Error>>
+{
+    if output.format != Format::Rss {
+        return Err(format!("With --hook, only rss outputs can be updated, not {:?}", output.format).into());
+    }
+    let path = match &output.file {
+        Some(file) if file != "-" => expand_path(file, vars)?,
+        _ => return Err("With --hook, each output needs a file".into()),
+    };
+    if !Path::new(&path).exists() {
+        return Ok(());
+    }
+
+    let old = rss::Channel::read_from(io::BufReader::new(fs::File::open(&path)?))?;
+    info!("Adding {} items to the {} items of {}", chan.items().len(), old.items().len(), path);
+
+    let key = |x: &rss::Item| x.guid().map(|x| x.value().to_string())
+        .or_else(|| Some(format!("{}#{}", x.link()?, x.pub_date()?)));
+    let new_keys: Vec<_> = chan.items().iter().filter_map(key).collect();
+
+    let too_old = |x: &rss::Item| match (min_time, x.pub_date()) {
+        (Some(min_time), Some(date)) => chrono::DateTime::parse_from_rfc2822(date)
+            .is_ok_and(|date| date.timestamp() < min_time),
+        _ => false,
+    };
+    let mut items: Vec<_> = old.items().iter()
+        .filter(|x| key(x).is_none_or(|k| !new_keys.contains(&k)) && !too_old(x))
+        .cloned()
+        .collect();
+    items.extend_from_slice(chan.items());
+    if let Some(limit) = limit {
+        // keep the newest items
+        items.drain(..items.len().saturating_sub(limit));
+    }
+
+    if old.pub_date().is_some() {
+        chan.set_pub_date(old.pub_date().map(str::to_string));
+    }
+    chan.set_items(items);
+    Ok(())
+}
+
+/// Returns a unique ID for the entry as IRI; this is the GUID, if any, or the
+/// link with the commit as fragment, because the link alone is the same for
+/// all changes of a page; without link, it is a hash of commit and path
+pub fn entry_id(e: &Entry) -> String {
+    match (e.item.guid(), e.item.link()) {
+        (Some(guid), _) if url::Url::parse(guid.value()).is_ok() => guid.value().to_string(),
+        (Some(guid), _) => format!("urn:sha1:{}", guid.value()),
+        (None, Some(link)) => format!("{}#{}", link, e.commit),
+        (None, None) => {
+            let id = git2::Oid::hash_object(
+                git2::ObjectType::Blob, format!("{}:{}", e.commit, e.path).as_bytes()
+            ).unwrap();
+            format!("urn:sha1:{}", id)
+        }
+    }
+}
+
+/// Settings that apply to all outputs
+pub struct Options {
+    /// indent character and size for pretty printing
+    pub pretty: Option<(u8, usize)>,
+    pub xml: xml::Options,
+    pub mail: MailOptions,
+}
+
+/// Renders the channel in the format of the output; `vars` are the
+/// placeholders for the URL of the output
+pub fn render(output: &Output, chan: &rss::Channel, entries: &[Entry], opts: &Options,
+              vars: &BTreeMap<&str, String>) -> Result<Vec<u8>, Box<dyn error::Error>>
+{
+    let self_url = output.url.as_deref().map(|x| expand_path(x, vars)).transpose()?;
+
+    let buf = match output.format {
+        Format::Rss => {
+            let mut chan = chan.clone();
+            if let Some(url) = self_url {
+                chan.atom_ext.get_or_insert_with(Default::default).links.push(rss::extension::atom::Link {
+                    href: url,
+                    rel: "self".into(),
+                    mime_type: Some("application/rss+xml".into()),
+                    ..Default::default()
+                });
+            }
+
+            // the rss crate writes elements and attributes in a fixed order,
+            // so the output only changes, if the content changes
+            let mut buf = match opts.pretty {
+                Some((indent_char, indent_size)) => {
+                    let mut buf = chan.pretty_write_to(Vec::new(), indent_char, indent_size)?;
+                    buf.push(b'\n');
+                    buf
+                }
+                None => chan.write_to(Vec::new())?,
+            };
+            buf = xml::finish(&String::from_utf8(buf)?, &opts.xml);
+            buf
+        }
+
+        Format::Atom => {
+            let mut feed = atom::feed(chan, entries);
+            archive::atom_links(chan, &mut feed);
+            if let Some(url) = self_url {
+                feed.links.push(::atom_syndication::Link {
+                    href: url,
+                    rel: "self".into(),
+                    mime_type: Some("application/atom+xml".into()),
+                    ..Default::default()
+                });
+            }
+            let config = ::atom_syndication::WriteConfig {
+                write_document_declaration: false,
+                indent_size: None,
+            };
+            let mut buf = feed.write_with_config(Vec::new(), config)?;
+            if let Some((indent_char, indent_size)) = opts.pretty {
+                buf = xml::indent(&buf, indent_char, indent_size)?;
+            }
+            xml::finish(&String::from_utf8(buf)?, &opts.xml)
+        }
+
+        Format::Markdown => markdown::document(chan, entries).into_bytes(),
+
+        Format::Sitemap => xml::finish(&sitemap::document(entries), &opts.xml),
+
+        Format::NdJson => ndjson::document(entries)?,
+
+        Format::Ics => ics::document(chan, entries).into_bytes(),
+
+        Format::Mbox | Format::Maildir => mail::mbox(chan, entries, &opts.mail)?.into_bytes(),
+
+        Format::Opml => return Err("The OPML list can't be rendered for a channel".into()),
+
+        Format::Template | Format::Html => {
+            template::render(output.template.as_deref(), "changes.html", chan, entries, self_url.as_deref())?
+        }
+
+        Format::HFeed => {
+            template::render(output.template.as_deref(), "h-feed.html", chan, entries, self_url.as_deref())?
+        }
+
+        Format::JsonFeed => {
+            let mut feed = jsonfeed::feed(chan, entries);
+            if let Some(url) = self_url {
+                feed["feed_url"] = url.into();
+            }
+            let mut buf = Vec::new();
+            match opts.pretty {
+                Some((indent_char, indent_size)) => {
+                    let indent = vec![indent_char; indent_size];
+                    let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+                    feed.serialize(&mut serde_json::Serializer::with_formatter(&mut buf, formatter))?;
+                }
+                None => serde_json::to_writer(&mut buf, &feed)?,
+            }
+            buf.push(b'\n');
+            buf
+        }
+    };
+
+    Ok(buf)
+}
+
+/// Replaces the placeholders like `{section}` in the path template
+pub fn expand_path(template: &str, vars: &BTreeMap<&str, String>) -> Result<String, Box<dyn error::Error>> {
+    let mut path = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}')
+            .ok_or_else(|| format!("Unterminated placeholder in output file {}", template))?;
+        let name = &rest[start + 1..start + end];
+        let value = vars.get(name)
+            .ok_or_else(|| format!("Unknown placeholder {{{}}} in output file {}", name, template))?;
+
+        path.push_str(&rest[..start]);
+        path.push_str(value);
+        rest = &rest[start + end + 1..];
+    }
+
+    path.push_str(rest);
+    Ok(path)
+}
+
+/// Writes the gzip compressed data to `file`.gz, if the content of `file`
+/// changed or the compressed file is missing
+fn write_gzip(file: &str, old: Option<&[u8]>, data: &[u8]) -> Result<(), Box<dyn error::Error>> {
+    let gz_file = format!("{}.gz", file);
+    if old == Some(data) && Path::new(&gz_file).exists() {
+        info!("Keeping {}, because {} is unchanged", gz_file, file);
+        return Ok(());
+    }
+
+    // without a name and time in the header, the same data gives the same file
+    let mut encoder = flate2::GzBuilder::new().write(Vec::new(), flate2::Compression::best());
+    encoder.write_all(data)?;
+    fs::write(&gz_file, encoder.finish()?)
+        .map_err(|err| format!("Failed to write output file {}: {}", gz_file, err))?;
+    Ok(())
+}
+
+/// Writes the rendered data to the file of the output; placeholders in the
+/// file name get replaced by `vars` and missing directories get created; with
+/// `gzip`, a compressed copy gets written, too
+pub fn write(output: &Output, vars: &BTreeMap<&str, String>, data: &[u8], gzip: bool)
+             -> Result<(), Box<dyn error::Error>>
+{
+    match output.file {
+        Some(ref template) if output.format == Format::Maildir => {
+            let dir = expand_path(template, vars)?;
+            let count = mail::write_maildir(&dir, data)?;
+            info!("Delivered {} new messages to {}", count, dir);
+        }
+        None if output.format == Format::Maildir => return Err("An output with format maildir needs a file".into()),
+        Some(ref template) => {
+            let file = expand_path(template, vars)?;
+            info!("Writing {:?} output to {}", output.format, file);
+
+            if let Some(dir) = Path::new(&file).parent().filter(|x| !x.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)
+                    .map_err(|err| format!("Failed to create directory {}: {}", dir.display(), err))?;
+            }
+
+            let old = if gzip { fs::read(&file).ok() } else { None };
+            fs::write(&file, data)
+                .map_err(|err| format!("Failed to write output file {}: {}", file, err))?;
+            if gzip {
+                write_gzip(&file, old.as_deref(), data)?;
+            }
+        }
+        None => io::stdout().write_all(data)?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_templates() {
+        let vars = [("section", "blog".to_string()), ("lang", "de".to_string())].into();
+        assert_eq!(expand_path("www/{section}/{lang}.xml", &vars).unwrap(), "www/blog/de.xml");
+        assert_eq!(expand_path("feed.xml", &vars).unwrap(), "feed.xml");
+        assert!(expand_path("{author}.xml", &vars).is_err());
+        assert!(expand_path("{section.xml", &vars).is_err());
+    }
+
+    #[test]
+    fn self_urls() {
+        let conf = yaml_rust::YamlLoader::load_from_str(
+            "self-url: https://example.org/rss\n\
+             outputs:\n  - {format: rss}\n  - {format: atom}\n  - {format: jsonfeed, url: 'https://example.org/{lang}.json'}\n"
+        ).unwrap().remove(0);
+        let outputs = from_conf(&conf).unwrap();
+        assert_eq!(outputs.iter().map(|x| x.url.as_deref()).collect::<Vec<_>>(),
+                   [Some("https://example.org/rss"), None, Some("https://example.org/{lang}.json")]);
+
+        let chan = rss::Channel::default();
+        let opts = Options {
+            pretty: None,
+            xml: xml::Options::from_conf(&Yaml::BadValue).unwrap(),
+            mail: MailOptions::from_conf(&Yaml::BadValue).unwrap(),
+        };
+        let vars = [("lang", "de".to_string())].into();
+        let data = render(&outputs[2], &chan, &[], &opts, &vars).unwrap();
+        assert!(String::from_utf8_lossy(&data).contains("\"feed_url\":\"https://example.org/de.json\""));
+        let data = render(&outputs[0], &chan, &[], &opts, &vars).unwrap();
+        assert!(String::from_utf8_lossy(&data).contains("<atom:link href=\"https://example.org/rss\" rel=\"self\""));
+    }
+
+    #[test]
+    fn output_map() {
+        let conf = yaml_rust::YamlLoader::load_from_str(
+            "self-url: https://example.org/rss\noutputs: {rss: www/feed.xml, atom: '-'}\n"
+        ).unwrap().remove(0);
+        let outputs = from_conf(&conf).unwrap();
+        assert_eq!(outputs.iter().map(|x| (x.format, x.file.as_deref(), x.url.as_deref())).collect::<Vec<_>>(),
+                   [(Format::Rss, Some("www/feed.xml"), Some("https://example.org/rss")),
+                    (Format::Atom, None, None)]);
+
+        let conf = yaml_rust::YamlLoader::load_from_str("outputs: {template: x.html}").unwrap().remove(0);
+        assert!(from_conf(&conf).is_err());
+    }
+
+    fn item(guid: &str, date: &str) -> rss::Item {
+        let mut item = rss::Item::default();
+        item.set_guid(rss::Guid { value: guid.to_string(), permalink: false });
+        item.set_pub_date(date.to_string());
+        item
+    }
+
+    #[test]
+    fn merge_existing_items() {
+        let file = std::env::temp_dir().join(format!("gitlog2rss-merge-{}.xml", std::process::id()));
+        let mut old = rss::Channel::default();
+        old.set_items(vec![
+            item("a", "Mon, 01 Jan 2024 00:00:00 +0000"),
+            item("b", "Mon, 01 Jul 2024 00:00:00 +0000"),
+            item("c", "Wed, 01 Jan 2025 00:00:00 +0000"),
+        ]);
+        old.write_to(fs::File::create(&file).unwrap()).unwrap();
+
+        let output = Output {
+            format: Format::Rss,
+            file: Some(file.to_str().unwrap().to_string()),
+            url: None,
+            template: None,
+        };
+        let guids = |min_time, limit| {
+            let mut chan = rss::Channel::default();
+            chan.set_items(vec![item("c", "Wed, 01 Jan 2025 00:00:00 +0000"),
+                                item("d", "Sat, 01 Feb 2025 00:00:00 +0000")]);
+            merge_existing(&output, &BTreeMap::new(), &mut chan, min_time, limit).unwrap();
+            chan.items().iter().map(|x| x.guid().unwrap().value().to_string()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(guids(None, None), ["a", "b", "c", "d"]);
+        // 2024-06-01
+        assert_eq!(guids(Some(1_717_200_000), None), ["b", "c", "d"]);
+        assert_eq!(guids(None, Some(3)), ["b", "c", "d"]);
+
+        let atom = Output { format: Format::Atom, ..output.clone() };
+        assert!(merge_existing(&atom, &BTreeMap::new(), &mut rss::Channel::default(), None, None).is_err());
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn gzip_copy() {
+        use std::io::Read;
+
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-gzip-{}", std::process::id()));
+        let output = Output {
+            format: Format::Rss,
+            file: Some(format!("{}/{{lang}}/feed.xml", dir.display())),
+            url: None,
+            template: None,
+        };
+        let vars = [("lang", "de".to_string())].into();
+        let gz_file = dir.join("de/feed.xml.gz");
+
+        write(&output, &vars, b"<rss/>", true).unwrap();
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(fs::File::open(&gz_file).unwrap()).read_to_string(&mut text).unwrap();
+        assert_eq!(text, "<rss/>");
+
+        // an unchanged file keeps its compressed copy
+        fs::write(&gz_file, "old").unwrap();
+        write(&output, &vars, b"<rss/>", true).unwrap();
+        assert_eq!(fs::read(&gz_file).unwrap(), b"old");
+        write(&output, &vars, b"<rss></rss>", true).unwrap();
+        assert_ne!(fs::read(&gz_file).unwrap(), b"old");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
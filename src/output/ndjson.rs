@@ -0,0 +1,52 @@
+//! One JSON object per line for each item, see https://github.com/ndjson/ndjson-spec
+
+use crate::{datetime, Entry};
+use serde_json::json;
+
+/// Returns a line with a JSON object for each entry
+pub fn document(entries: &[Entry]) -> Result<Vec<u8>, serde_json::Error> {
+    let mut buf = Vec::new();
+    for e in entries {
+        let line = json!({
+            "commit": e.commit.to_string(),
+            "status": format!("{:?}", e.status).to_lowercase(),
+            "path": e.path,
+            "url": e.item.link(),
+            "author_name": e.author_name,
+            "author_email": e.author_email,
+            "date": datetime(&e.time).to_rfc3339(),
+            "title": e.item.title(),
+        });
+        serde_json::to_writer(&mut buf, &line)?;
+        buf.push(b'\n');
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        let mut item = rss::Item::default();
+        item.set_title("New \"a\"\n".to_string());
+        let entry = Entry {
+            time: git2::Time::new(0, 120),
+            commit: git2::Oid::zero(),
+            status: git2::Delta::Deleted,
+            path: "a.md".to_string(),
+            page: None,
+            author_name: "Ann".to_string(),
+            author_email: "ann@example.org".to_string(),
+            author_uri: None,
+            item,
+        };
+
+        let doc = String::from_utf8(document(&[entry.clone(), entry]).unwrap()).unwrap();
+        let line = "{\"author_email\":\"ann@example.org\",\"author_name\":\"Ann\",\
+                    \"commit\":\"0000000000000000000000000000000000000000\",\"date\":\"1970-01-01T02:00:00+02:00\",\
+                    \"path\":\"a.md\",\"status\":\"deleted\",\"title\":\"New \\\"a\\\"\\n\",\"url\":null}\n";
+        assert_eq!(doc, line.repeat(2));
+    }
+}
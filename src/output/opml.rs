@@ -0,0 +1,27 @@
+//! OPML list of the generated feeds for subscribing to all of them at once,
+//! see http://opml.org/spec2.opml
+
+use crate::html_escape;
+use std::fmt::Write;
+
+/// A feed in the list
+pub struct Feed {
+    pub title: String,
+    /// the public URL of the feed
+    pub url: String,
+    /// the website of the feed
+    pub html_url: String,
+}
+
+/// Returns the OPML document with one outline for each feed
+pub fn document(title: &str, feeds: &[Feed]) -> String {
+    let mut doc = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    let _ = writeln!(doc, "  <head><title>{}</title></head>\n  <body>", html_escape(title));
+    for feed in feeds {
+        let _ = writeln!(doc, "    <outline type=\"rss\" text=\"{0}\" title=\"{0}\" xmlUrl=\"{1}\" htmlUrl=\"{2}\"/>",
+                         html_escape(&feed.title), html_escape(&feed.url), html_escape(&feed.html_url));
+    }
+    doc.push_str("  </body>\n</opml>\n");
+
+    doc
+}
@@ -0,0 +1,55 @@
+//! Sitemap of the pages with the date of their last change, see
+//! https://www.sitemaps.org/protocol.html
+
+use crate::{datetime, html_escape, Entry};
+use git2::Delta;
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Returns the sitemap of the pages of the entries without the removed ones;
+/// the URLs are percent-encoded as the protocol requires, also with the
+/// config entry `url-encoding: utf-8`
+pub fn document(entries: &[Entry]) -> String {
+    // the entries are sorted by date, so the last one is the newest change
+    let mut pages = BTreeMap::new();
+    for e in entries {
+        if let Some(ref page) = e.page {
+            pages.insert(page.as_str(), e);
+        }
+    }
+
+    let mut doc = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                                <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (page, e) in pages {
+        if e.status == Delta::Deleted {
+            continue;
+        }
+        let url = url::Url::parse(page).map_or_else(|_| page.to_string(), String::from);
+        let _ = writeln!(doc, "  <url><loc>{}</loc><lastmod>{}</lastmod></url>",
+                         html_escape(&url), datetime(&e.time).to_rfc3339());
+    }
+    doc.push_str("</urlset>\n");
+
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encoded_locations() {
+        let entry = Entry {
+            time: git2::Time::new(0, 0),
+            commit: git2::Oid::zero(),
+            status: Delta::Added,
+            path: "src/über.md".to_string(),
+            page: Some("https://example.org/über uns.html".to_string()),
+            author_name: String::new(),
+            author_email: String::new(),
+            author_uri: None,
+            item: Default::default(),
+        };
+        let doc = document(&[entry]);
+        assert!(doc.contains("<loc>https://example.org/%C3%BCber%20uns.html</loc>"), "{}", doc);
+    }
+}
@@ -0,0 +1,112 @@
+//! Output of a user-supplied Tera template, see https://keats.github.io/tera/docs/
+
+use super::entry_id;
+use crate::{datetime, html_escape, Entry};
+use serde_json::{json, Value};
+use std::error;
+
+/// Returns the data of the channel and the items for the template
+fn context(chan: &rss::Channel, entries: &[Entry], self_url: Option<&str>) -> Value {
+    let items: Vec<_> = entries.iter()
+        .map(|e| {
+            let item = &e.item;
+            json!({
+                "id": entry_id(e),
+                "title": item.title(),
+                "link": item.link(),
+                "description": item.description(),
+                "content": item.content(),
+                "author": item.author(),
+                "author_name": e.author_name,
+                "author_email": e.author_email,
+                "author_uri": e.author_uri,
+                "pub_date": item.pub_date(),
+                "date": datetime(&e.time).to_rfc3339(),
+                "guid": item.guid().map(|x| x.value()),
+                "categories": item.categories().iter().map(|x| x.name()).collect::<Vec<_>>(),
+                "enclosure": item.enclosure().map(|x| json!({
+                    "url": x.url(),
+                    "length": x.length(),
+                    "mime_type": x.mime_type(),
+                })),
+                "comments": item.comments(),
+                "commit": e.commit.to_string(),
+                "path": e.path,
+                "status": format!("{:?}", e.status).to_lowercase(),
+            })
+        })
+        .collect();
+
+    json!({
+        "channel": {
+            "title": chan.title(),
+            "link": chan.link(),
+            "description": chan.description(),
+            "language": chan.language(),
+            "copyright": chan.copyright(),
+            "managing_editor": chan.managing_editor(),
+            "webmaster": chan.webmaster(),
+            "pub_date": chan.pub_date(),
+            "last_build_date": chan.last_build_date(),
+            "generator": chan.generator(),
+            "ttl": chan.ttl(),
+            "image": chan.image().map(|x| x.url()),
+            "self_url": self_url,
+        },
+        "items": items,
+    })
+}
+
+/// The built-in templates
+const BUILTIN: [(&str, &str); 2] = [
+    ("changes.html", include_str!("changes.html")),
+    ("h-feed.html", include_str!("h-feed.html")),
+];
+
+/// Renders the template file or, without it, the built-in template `builtin`;
+/// values are escaped, if the file ends with `.html`, `.htm` or `.xml`
+pub fn render(path: Option<&str>, builtin: &str, chan: &rss::Channel, entries: &[Entry],
+              self_url: Option<&str>) -> Result<Vec<u8>, Box<dyn error::Error>>
+{
+    let mut tera = tera::Tera::default();
+    // Tera escapes also / and '
+    tera.set_escape_fn(html_escape);
+    let name = match path {
+        Some(path) => {
+            tera.add_template_file(path, None)?;
+            path
+        }
+        None => {
+            let (name, text) = BUILTIN.iter().find(|(name, _)| *name == builtin)
+                .ok_or_else(|| format!("Unknown built-in template {}", builtin))?;
+            tera.add_raw_template(name, text)?;
+            name
+        }
+    };
+    let ctx = tera::Context::from_value(context(chan, entries, self_url))?;
+    Ok(tera.render(name, &ctx)?.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn escaped_values() {
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-template-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut chan = rss::Channel::default();
+        chan.set_title("Tom & Jerry");
+
+        let txt = dir.join("feed.txt");
+        fs::write(&txt, "{{ channel.title }} {{ items | length }}").unwrap();
+        assert_eq!(render(Some(txt.to_str().unwrap()), "changes.html", &chan, &[], None).unwrap(), b"Tom & Jerry 0");
+
+        let xml = dir.join("feed.xml");
+        fs::write(&xml, "<t>{{ channel.title }}</t>").unwrap();
+        assert_eq!(render(Some(xml.to_str().unwrap()), "changes.html", &chan, &[], None).unwrap(), b"<t>Tom &amp; Jerry</t>");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+//! Filters and formatters in WebAssembly from the config entry `plugins`
+//!
+//! A plugin is a module without imports, which exports its `memory`,
+//! `alloc(len: i32) -> i32` for the input and the optional hooks
+//! `filter_item(ptr: i32, len: i32) -> i64` and `finalize_channel(ptr: i32,
+//! len: i32) -> i64`. The input of the hooks is JSON of the item or channel;
+//! they return 0 for no change or the pointer to the JSON of the changes in
+//! the upper and its length in the lower 32 bits. Each call gets the fuel of
+//! the config entry `plugin-fuel`, so that a looping plugin fails instead of
+//! hanging.
+
+use crate::Entry;
+use serde_json::{json, Value};
+use std::error;
+use yaml_rust::Yaml;
+
+/// Returns the JSON of the item for `filter_item`
+fn item_json(e: &Entry) -> Value {
+    json!({
+        "commit": e.commit.to_string(),
+        "path": e.path,
+        "status": format!("{:?}", e.status).to_lowercase(),
+        "title": e.item.title(),
+        "link": e.item.link(),
+        "description": e.item.description(),
+        "content": e.item.content(),
+        "author": {"name": e.author_name, "email": e.author_email},
+        "categories": e.item.categories().iter().map(|x| x.name()).collect::<Vec<_>>(),
+    })
+}
+
+/// Applies the changes of `filter_item` to the item; returns false, if the
+/// item is dropped with `"drop": true`
+fn apply_item(e: &mut Entry, changes: &Value) -> bool {
+    if changes["drop"].as_bool().unwrap_or(false) {
+        return false;
+    }
+
+    let text = |key: &str| changes[key].as_str().map(str::to_string);
+    if let Some(x) = text("title") {
+        e.item.set_title(x);
+    }
+    if let Some(x) = text("link") {
+        e.item.set_link(x);
+    }
+    if let Some(x) = text("description") {
+        e.item.set_description(x);
+    }
+    if let Some(x) = text("content") {
+        e.item.set_content(x);
+    }
+    if let Some(list) = changes["categories"].as_array() {
+        e.item.set_categories(list.iter()
+            .filter_map(|x| x.as_str())
+            .map(|name| rss::Category { name: name.to_string(), domain: None })
+            .collect::<Vec<_>>());
+    }
+    true
+}
+
+/// Applies the changes of `finalize_channel` to the channel
+fn apply_channel(chan: &mut rss::Channel, changes: &Value) {
+    if let Some(x) = changes["title"].as_str() {
+        chan.set_title(x);
+    }
+    if let Some(x) = changes["link"].as_str() {
+        chan.set_link(x);
+    }
+    if let Some(x) = changes["description"].as_str() {
+        chan.set_description(x);
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+mod wasm {
+    use log::debug;
+    use serde_json::Value;
+    use std::{
+        collections::HashMap,
+        convert::TryFrom,
+        error,
+        fs,
+        sync::{Mutex, OnceLock},
+        time::SystemTime,
+    };
+    use wasmtime::{Config, Engine, Instance, Memory, Module, Store, TypedFunc};
+
+    /// A compiled module with the modification time of its file
+    type Compiled = (Option<SystemTime>, Module);
+
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    /// the compiled modules by path, so that a server compiles each plugin
+    /// only once
+    static MODULES: OnceLock<Mutex<HashMap<String, Compiled>>> = OnceLock::new();
+
+    pub struct Plugin {
+        name: String,
+        store: Store<()>,
+        /// the fuel for each call of a hook
+        fuel: u64,
+        memory: Memory,
+        alloc: TypedFunc<i32, i32>,
+        filter_item: Option<TypedFunc<(i32, i32), i64>>,
+        finalize_channel: Option<TypedFunc<(i32, i32), i64>>,
+    }
+
+    pub fn load_all(paths: &[&str], fuel: u64) -> Result<Vec<Plugin>, Box<dyn error::Error>> {
+        let engine = match ENGINE.get() {
+            Some(engine) => engine,
+            None => {
+                let mut config = Config::new();
+                config.consume_fuel(true);
+                let engine = Engine::new(&config)
+                    .map_err(|err| format!("Failed to set up WebAssembly: {}", err))?;
+                ENGINE.get_or_init(|| engine)
+            }
+        };
+        paths.iter().map(|path| Plugin::load(engine, path, fuel)).collect()
+    }
+
+    /// Returns the compiled module of the file, which is compiled again only
+    /// after a change
+    fn module(engine: &Engine, path: &str) -> Result<Module, Box<dyn error::Error>> {
+        let mtime = fs::metadata(path).and_then(|x| x.modified()).ok();
+        let mut modules = MODULES.get_or_init(Default::default).lock().unwrap();
+        if let Some((time, module)) = modules.get(path) {
+            if mtime.is_some() && *time == mtime {
+                return Ok(module.clone());
+            }
+        }
+
+        debug!("Compiling plugin {}", path);
+        let module = Module::from_file(engine, path)
+            .map_err(|err| format!("Failed to load plugin {}: {}", path, err))?;
+        modules.insert(path.to_string(), (mtime, module.clone()));
+        Ok(module)
+    }
+
+    impl Plugin {
+        /// Instantiates the plugin; each generation gets fresh instances with
+        /// their own memory
+        fn load(engine: &Engine, path: &str, fuel: u64) -> Result<Self, Box<dyn error::Error>> {
+            let module = module(engine, path)?;
+            let mut store = Store::new(engine, ());
+            let instance = Instance::new(&mut store, &module, &[])
+                .map_err(|err| format!("Failed to instantiate plugin {}: {}", path, err))?;
+            let memory = instance.get_memory(&mut store, "memory")
+                .ok_or_else(|| format!("Plugin {} exports no memory", path))?;
+            let alloc = instance.get_typed_func(&mut store, "alloc")
+                .map_err(|err| format!("Plugin {} exports no alloc: {}", path, err))?;
+            let filter_item = instance.get_typed_func(&mut store, "filter_item").ok();
+            let finalize_channel = instance.get_typed_func(&mut store, "finalize_channel").ok();
+
+            Ok(Plugin { name: path.to_string(), store, fuel, memory, alloc, filter_item, finalize_channel })
+        }
+
+        /// Calls the hook with the JSON input and returns the JSON of the
+        /// changes or `None`
+        fn call(&mut self, hook: TypedFunc<(i32, i32), i64>, input: &Value)
+                -> Result<Option<Value>, Box<dyn error::Error>>
+        {
+            let input = serde_json::to_vec(input)?;
+            let len = i32::try_from(input.len())?;
+            // running out of fuel traps the call
+            self.store.set_fuel(self.fuel)?;
+            let ptr = self.alloc.call(&mut self.store, len)
+                .map_err(|err| format!("Plugin {} failed in alloc: {}", self.name, err))?;
+            self.memory.write(&mut self.store, ptr as u32 as usize, &input)?;
+
+            let result = hook.call(&mut self.store, (ptr, len))
+                .map_err(|err| format!("Plugin {} failed: {}", self.name, err))?;
+            if result == 0 {
+                return Ok(None);
+            }
+
+            let (ptr, len) = ((result as u64 >> 32) as usize, (result as u64 & 0xffff_ffff) as usize);
+            let data = self.memory.data(&self.store).get(ptr..ptr + len)
+                .ok_or_else(|| format!("Plugin {} returned an invalid range", self.name))?;
+            Ok(Some(serde_json::from_slice(data)
+                    .map_err(|err| format!("Plugin {} returned invalid JSON: {}", self.name, err))?))
+        }
+
+        pub fn filter_item(&mut self, input: &Value) -> Result<Option<Value>, Box<dyn error::Error>> {
+            match self.filter_item.clone() {
+                Some(hook) => self.call(hook, input),
+                None => Ok(None),
+            }
+        }
+
+        pub fn finalize_channel(&mut self, input: &Value) -> Result<Option<Value>, Box<dyn error::Error>> {
+            match self.finalize_channel.clone() {
+                Some(hook) => self.call(hook, input),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+mod wasm {
+    use serde_json::Value;
+    use std::error;
+
+    /// There are no plugins without the feature wasm-plugins
+    pub enum Plugin {}
+
+    pub fn load_all(paths: &[&str], _: u64) -> Result<Vec<Plugin>, Box<dyn error::Error>> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        Err("The config entry 'plugins' needs gitlog2rss built with the feature wasm-plugins".into())
+    }
+
+    impl Plugin {
+        pub fn filter_item(&mut self, _: &Value) -> Result<Option<Value>, Box<dyn error::Error>> {
+            match *self {}
+        }
+
+        pub fn finalize_channel(&mut self, _: &Value) -> Result<Option<Value>, Box<dyn error::Error>> {
+            match *self {}
+        }
+    }
+}
+
+/// The plugins in the order of the config entry
+#[derive(Default)]
+pub struct Plugins {
+    list: Vec<wasm::Plugin>,
+}
+
+impl Plugins {
+    /// Loads the plugins of the config entry `plugins` with the fuel of
+    /// `plugin-fuel` for each call
+    pub fn from_conf(conf: &Yaml) -> Result<Self, Box<dyn error::Error>> {
+        let paths: Vec<_> = match &conf["plugins"] {
+            Yaml::Array(list) => list.iter()
+                .map(|x| x.as_str().ok_or("Invalid value in config entry 'plugins'"))
+                .collect::<Result<_, _>>()?,
+            Yaml::String(path) => vec![path.as_str()],
+            Yaml::BadValue => return Ok(Plugins::default()),
+            _ => return Err("Invalid value of config entry 'plugins': expected a list".into()),
+        };
+
+        let fuel = match &conf["plugin-fuel"] {
+            Yaml::Integer(n) if *n > 0 => *n as u64,
+            Yaml::BadValue => 1_000_000_000,
+            _ => return Err("Invalid value of config entry 'plugin-fuel'".into()),
+        };
+
+        Ok(Plugins { list: wasm::load_all(&paths, fuel)? })
+    }
+
+    /// Lets the plugins change or drop the items
+    pub fn filter_items(&mut self, entries: &mut Vec<Entry>) -> Result<(), Box<dyn error::Error>> {
+        for plugin in &mut self.list {
+            let mut err = None;
+            entries.retain_mut(|e| {
+                if err.is_some() {
+                    return true;
+                }
+                match plugin.filter_item(&item_json(e)) {
+                    Ok(Some(changes)) => apply_item(e, &changes),
+                    Ok(None) => true,
+                    Err(x) => {
+                        err = Some(x);
+                        true
+                    }
+                }
+            });
+            if let Some(err) = err {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Lets the plugins change the channel
+    pub fn finalize_channel(&mut self, chan: &mut rss::Channel) -> Result<(), Box<dyn error::Error>> {
+        for plugin in &mut self.list {
+            let input = json!({
+                "title": chan.title(),
+                "link": chan.link(),
+                "description": chan.description(),
+                "items": chan.items().len(),
+            });
+            if let Some(changes) = plugin.finalize_channel(&input)? {
+                apply_channel(chan, &changes);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "wasm-plugins"))]
+mod tests {
+    use super::*;
+
+    /// sets the title of the items and loops in finalize_channel
+    const PLUGIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 60000) "{\"title\":\"Neu\"}")
+  (func (export "alloc") (param i32) (result i32) (i32.const 0))
+  (func (export "filter_item") (param i32 i32) (result i64)
+    (i64.or (i64.shl (i64.const 60000) (i64.const 32)) (i64.const 15)))
+  (func (export "finalize_channel") (param i32 i32) (result i64)
+    (loop $forever (br $forever))
+    (i64.const 0)))
+"#;
+
+    #[test]
+    fn fuel() {
+        let path = std::env::temp_dir().join(format!("gitlog2rss-plugin-{}.wasm", std::process::id()));
+        std::fs::write(&path, wat::parse_str(PLUGIN).unwrap()).unwrap();
+        let conf = yaml_rust::YamlLoader::load_from_str(&format!(
+            "{{plugins: ['{}'], plugin-fuel: 100000}}", path.display()
+        )).unwrap().remove(0);
+        let mut plugins = Plugins::from_conf(&conf).unwrap();
+
+        let mut entries = vec![Entry {
+            time: git2::Time::new(0, 0),
+            commit: git2::Oid::zero(),
+            status: git2::Delta::Added,
+            path: "src/a.md".to_string(),
+            page: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            author_uri: None,
+            item: rss::Item::default(),
+        }];
+        plugins.filter_items(&mut entries).unwrap();
+        assert_eq!(entries[0].item.title(), Some("Neu"));
+
+        let err = plugins.finalize_channel(&mut rss::Channel::default()).unwrap_err().to_string();
+        assert!(err.starts_with(&format!("Plugin {} failed: ", path.display())), "{}", err);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+//! The iTunes podcast elements from the config entry `podcast`, see
+//! https://help.apple.com/itc/podcasts_connect/#/itcb54353390
+
+use crate::Entry;
+use rss::extension::itunes::{ITunesCategory, ITunesChannelExtension, ITunesItemExtension, ITunesOwner};
+use std::error;
+use yaml_rust::Yaml;
+
+/// Returns the string value of the key in the section, e.g. for `explicit:
+/// false` a string from the boolean
+fn value(section: &Yaml, key: &str, name: &str) -> Result<Option<String>, Box<dyn error::Error>> {
+    match &section[key] {
+        Yaml::String(x) => Ok(Some(x.clone())),
+        Yaml::Integer(x) => Ok(Some(x.to_string())),
+        Yaml::Boolean(x) => Ok(Some(x.to_string())),
+        Yaml::BadValue => Ok(None),
+        _ => Err(format!("Invalid value of config entry '{}.{}'", name, key).into()),
+    }
+}
+
+/// Returns the iTunes elements of the channel or `None` without the config
+/// entry `podcast`
+pub fn channel(conf: &Yaml) -> Result<Option<ITunesChannelExtension>, Box<dyn error::Error>> {
+    match conf {
+        Yaml::Hash(_) => {}
+        Yaml::BadValue => return Ok(None),
+        _ => return Err("Invalid value of config entry 'podcast': expected a map".into()),
+    }
+
+    // a category with a subcategory is written like Arts/Design
+    let categories = match &conf["categories"] {
+        Yaml::Array(list) => list.iter().map(|x| {
+            let name = x.as_str().ok_or("Invalid value in config entry 'podcast.categories'")?;
+            let mut parts = name.splitn(2, '/').map(str::trim);
+            Ok(ITunesCategory {
+                text: parts.next().unwrap_or_default().to_string(),
+                subcategory: parts.next().map(|sub| Box::new(ITunesCategory {
+                    text: sub.to_string(),
+                    subcategory: None,
+                })),
+            })
+        }).collect::<Result<_, Box<dyn error::Error>>>()?,
+        Yaml::BadValue => Vec::new(),
+        _ => return Err("Invalid value of config entry 'podcast.categories': expected a list".into()),
+    };
+
+    let owner = match &conf["owner"] {
+        owner @ Yaml::Hash(_) => Some(ITunesOwner {
+            name: value(owner, "name", "podcast.owner")?,
+            email: value(owner, "email", "podcast.owner")?,
+        }),
+        Yaml::BadValue => None,
+        _ => return Err("Invalid value of config entry 'podcast.owner'".into()),
+    };
+
+    Ok(Some(ITunesChannelExtension {
+        author: value(conf, "author", "podcast")?,
+        categories,
+        image: value(conf, "image", "podcast")?,
+        explicit: value(conf, "explicit", "podcast")?,
+        complete: value(conf, "complete", "podcast")?,
+        new_feed_url: value(conf, "new-feed-url", "podcast")?,
+        owner,
+        subtitle: value(conf, "subtitle", "podcast")?,
+        summary: value(conf, "summary", "podcast")?,
+        keywords: value(conf, "keywords", "podcast")?,
+        r#type: value(conf, "type", "podcast")?,
+        ..Default::default()
+    }))
+}
+
+/// Adds the iTunes elements to the items with an enclosure; the values of
+/// `podcast.episodes` are taken by the path of the file
+pub fn apply(conf: &Yaml, entries: &mut [Entry]) -> Result<(), Box<dyn error::Error>> {
+    if conf.is_badvalue() {
+        return Ok(());
+    }
+    match &conf["episodes"] {
+        Yaml::Hash(_) | Yaml::BadValue => {}
+        _ => return Err("Invalid value of config entry 'podcast.episodes': expected a map".into()),
+    }
+
+    for e in entries.iter_mut().filter(|x| x.item.enclosure().is_some()) {
+        let episode = &conf["episodes"][e.path.as_str()];
+        let name = format!("podcast.episodes.{}", e.path);
+        if !matches!(episode, Yaml::Hash(_) | Yaml::BadValue) {
+            return Err(format!("Invalid value of config entry '{}'", name).into());
+        }
+
+        e.item.set_itunes_ext(ITunesItemExtension {
+            author: Some(e.author_name.clone()).filter(|x| !x.is_empty()),
+            duration: value(episode, "duration", &name)?,
+            episode: value(episode, "episode", &name)?,
+            season: value(episode, "season", &name)?,
+            episode_type: value(episode, "type", &name)?,
+            explicit: value(episode, "explicit", &name)?,
+            image: value(episode, "image", &name)?,
+            summary: value(episode, "summary", &name)?,
+            ..Default::default()
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conf() -> Yaml {
+        yaml_rust::YamlLoader::load_from_str("
+author: Jörg Sommer
+categories: [Technology, Arts/Design]
+explicit: false
+owner: {name: Jörg Sommer, email: joerg@jo-so.de}
+episodes:
+  src/podcast/folge-1.mp3: {duration: '42:17', episode: 1, season: 1}
+").unwrap().remove(0)
+    }
+
+    fn entry(path: &str, enclosure: bool) -> Entry {
+        let mut item = rss::Item::default();
+        if enclosure {
+            item.set_enclosure(rss::Enclosure::default());
+        }
+        Entry {
+            time: git2::Time::new(0, 0),
+            commit: git2::Oid::zero(),
+            status: git2::Delta::Added,
+            path: path.to_string(),
+            page: None,
+            author_name: "Ann Author".to_string(),
+            author_email: String::new(),
+            author_uri: None,
+            item,
+        }
+    }
+
+    #[test]
+    fn channel_elements() {
+        let itunes = channel(&conf()).unwrap().unwrap();
+        assert_eq!(itunes.author.as_deref(), Some("Jörg Sommer"));
+        assert_eq!(itunes.explicit.as_deref(), Some("false"));
+        assert_eq!(itunes.categories[0].text, "Technology");
+        assert!(itunes.categories[0].subcategory.is_none());
+        assert_eq!(itunes.categories[1].text, "Arts");
+        assert_eq!(itunes.categories[1].subcategory.as_ref().unwrap().text, "Design");
+        assert_eq!(itunes.owner.unwrap().email.as_deref(), Some("joerg@jo-so.de"));
+
+        assert!(channel(&Yaml::BadValue).unwrap().is_none());
+        assert!(channel(&Yaml::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn episodes() {
+        let mut entries = vec![
+            entry("src/podcast/folge-1.mp3", true),
+            entry("src/podcast/folge-2.mp3", true),
+            entry("src/blog/a.md", false),
+        ];
+        apply(&conf(), &mut entries).unwrap();
+
+        let first = entries[0].item.itunes_ext().unwrap();
+        assert_eq!(first.duration.as_deref(), Some("42:17"));
+        assert_eq!(first.episode.as_deref(), Some("1"));
+        assert_eq!(first.author.as_deref(), Some("Ann Author"));
+        assert!(entries[1].item.itunes_ext().unwrap().duration.is_none());
+        assert!(entries[2].item.itunes_ext().is_none());
+    }
+}
@@ -0,0 +1,223 @@
+//! Items for refs instead of file changes
+
+use crate::{expand, html_escape, rfc822_time, Entry};
+use git2::{Delta, Oid, Repository};
+use log::{debug, warn};
+use rss::{Guid, ItemBuilder};
+use std::error;
+use yaml_rust::Yaml;
+
+/// Returns one item for each tag; the date, author and message are those of
+/// the tag for annotated tags and those of the commit for lightweight tags
+pub fn tags(repo: &Repository, conf: &Yaml, base_url: &url::Url)
+            -> Result<Vec<Entry>, Box<dyn error::Error>>
+{
+    let title = conf["item-title-tag"].as_str().unwrap_or("Tag %tag");
+    let tag_url = conf["tag-url"].as_str();
+
+    let mut entries = Vec::new();
+    for reference in repo.references_glob("refs/tags/*")? {
+        let reference = reference?;
+        let name = reference.shorthand().unwrap_or_default().to_string();
+        let commit = match reference.peel_to_commit() {
+            Ok(commit) => commit,
+            Err(err) => {
+                warn!("Skipping tag {}, because it points to no commit: {}", name, err.message());
+                continue;
+            }
+        };
+
+        let (signature, message) = match reference.peel_to_tag() {
+            Ok(tag) => (tag.tagger().map(|x| x.to_owned()), tag.message().map(String::from)),
+            Err(_) => (None, None),
+        };
+        let signature = signature.unwrap_or_else(|| commit.author().to_owned());
+        let message = message.or_else(|| commit.message().map(String::from));
+
+        let vars = [("tag", name.clone()), ("H", commit.id().to_string())];
+        let link = match tag_url {
+            Some(template) => expand(template, &vars),
+            None => base_url.to_string(),
+        };
+        let id = Oid::hash_object(git2::ObjectType::Blob, format!("tag:{}:{}", name, commit.id()).as_bytes())?;
+
+        let author_name = signature.name().unwrap_or_default().to_string();
+        let author_email = signature.email().unwrap_or_default().to_string();
+        debug!("New rss item for tag {}", name);
+        entries.push(Entry {
+            time: signature.when(),
+            commit: commit.id(),
+            status: Delta::Added,
+            path: name,
+            page: None,
+            item: ItemBuilder::default()
+                .author(Some(format!("{} ({})", author_email, author_name)))
+                .pub_date(Some(rfc822_time(&signature.when())))
+                .title(Some(expand(title, &vars)))
+                .link(Some(link))
+                .description(message.filter(|x| !x.trim().is_empty())
+                             .map(|x| format!("<pre>{}</pre>", html_escape(x.trim()))))
+                .guid(Some(Guid { value: id.to_string(), permalink: false }))
+                .build(),
+            author_name,
+            author_email,
+            author_uri: None,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Returns one item for each destructive update in the reflogs of the refs:
+/// forced updates (the old commit is no ancestor of the new one), resets and
+/// deletions
+pub fn reflog(repo: &Repository, conf: &Yaml, base_url: &url::Url)
+              -> Result<Vec<Entry>, Box<dyn error::Error>>
+{
+    let names = match &conf["reflog-refs"] {
+        Yaml::Array(list) => list.iter()
+            .map(|x| x.as_str().ok_or("Invalid value in config entry 'reflog-refs'"))
+            .collect::<Result<Vec<_>, _>>()?,
+        Yaml::BadValue => vec!["HEAD"],
+        _ => return Err("Invalid value of config entry 'reflog-refs'".into()),
+    };
+
+    let mut entries = Vec::new();
+    for name in names {
+        for log in repo.reflog(name)?.iter() {
+            let (old, new) = (log.id_old(), log.id_new());
+            let message = log.message().unwrap_or_default();
+
+            let (status, text, default) = if new.is_zero() {
+                (Delta::Deleted, "item-title-reflog-deleted", "Deletion of %ref")
+            } else if message.starts_with("reset:") {
+                (Delta::Modified, "item-title-reflog-reset", "Reset of %ref")
+            } else if message.starts_with("checkout:") {
+                // HEAD moving to another branch
+                continue;
+            } else if !old.is_zero() && old != new && !repo.graph_descendant_of(new, old).unwrap_or(false) {
+                (Delta::Modified, "item-title-reflog-forced", "Forced update of %ref")
+            } else {
+                continue;
+            };
+
+            let short = |id: Oid| id.to_string()[..7].to_string();
+            let vars = [
+                ("ref", name.to_string()),
+                ("old", short(old)),
+                ("new", short(new)),
+            ];
+
+            let signature = log.committer();
+            let author_name = signature.name().unwrap_or_default().to_string();
+            let author_email = signature.email().unwrap_or_default().to_string();
+            let id = Oid::hash_object(
+                git2::ObjectType::Blob,
+                format!("reflog:{}:{}:{}:{}", name, old, new, signature.when().seconds()).as_bytes()
+            )?;
+
+            debug!("New rss item for {} of {} to {}", text, name, new);
+            entries.push(Entry {
+                time: signature.when(),
+                commit: if new.is_zero() { old } else { new },
+                status,
+                path: name.to_string(),
+                page: None,
+                item: ItemBuilder::default()
+                    .author(Some(format!("{} ({})", author_email, author_name)))
+                    .pub_date(Some(rfc822_time(&signature.when())))
+                    .title(Some(expand(conf[text].as_str().unwrap_or(default), &vars)))
+                    .link(Some(base_url.to_string()))
+                    .description(Some(format!(
+                        "<p>{} → {}</p>\n<p>{}</p>",
+                        vars[1].1, vars[2].1, html_escape(message)
+                    )))
+                    .guid(Some(Guid { value: id.to_string(), permalink: false }))
+                    .build(),
+                author_name,
+                author_email,
+                author_uri: None,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    #[test]
+    fn tag_items() {
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-tags-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+
+        let blob = repo.blob(b"# A\n").unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("a.md", blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let sig = git2::Signature::new("Ann Author", "ann@example.org", &git2::Time::new(1_700_000_000, 0)).unwrap();
+        let commit = repo.commit(Some("HEAD"), &sig, &sig, "Add a", &tree, &[]).unwrap();
+        let commit = repo.find_object(commit, None).unwrap();
+
+        repo.tag_lightweight("v1", &commit, false).unwrap();
+        let tagger = git2::Signature::new("Bea Bot", "bea@example.org", &git2::Time::new(1_700_000_600, 0)).unwrap();
+        repo.tag("v2", &commit, &tagger, "Version <2>\n", false).unwrap();
+        // tags of no commit are skipped
+        repo.tag("blob", &repo.find_object(blob, None).unwrap(), &tagger, "A blob", false).unwrap();
+
+        let conf = YamlLoader::load_from_str("{item-title-tag: 'Version %tag', tag-url: 'https://example.org/t/%tag'}")
+            .unwrap().remove(0);
+        let mut entries = tags(&repo, &conf, &url::Url::parse("https://example.org/").unwrap()).unwrap();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(entries.len(), 2);
+
+        let v1 = &entries[0].item;
+        assert_eq!((v1.title(), v1.link()), (Some("Version v1"), Some("https://example.org/t/v1")));
+        assert_eq!(v1.author(), Some("ann@example.org (Ann Author)"));
+        assert_eq!(v1.description(), Some("<pre>Add a</pre>"));
+
+        let v2 = &entries[1].item;
+        assert_eq!(v2.author(), Some("bea@example.org (Bea Bot)"));
+        assert_eq!(v2.description(), Some("<pre>Version &lt;2&gt;</pre>"));
+        assert_eq!(entries[1].time.seconds(), 1_700_000_600);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reflog_items() {
+        let dir = std::env::temp_dir().join(format!("gitlog2rss-reflog-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+
+        let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+        let sig = git2::Signature::now("Ann Author", "ann@example.org").unwrap();
+        let first = repo.commit(Some("HEAD"), &sig, &sig, "First", &tree, &[]).unwrap();
+        let first = repo.find_commit(first).unwrap();
+        let second = repo.commit(Some("HEAD"), &sig, &sig, "Second", &tree, &[&first]).unwrap();
+        let branch = repo.head().unwrap().name().unwrap().to_string();
+
+        repo.reference(&branch, first.id(), true, "reset: moving to HEAD~").unwrap();
+        let other = repo.commit(None, &sig, &sig, "Other", &tree, &[&first]).unwrap();
+        repo.reference(&branch, other, true, "commit: Other").unwrap();
+        repo.reference(&branch, second, true, "push <forced>").unwrap();
+
+        let conf = YamlLoader::load_from_str(&format!("{{reflog-refs: ['{}'], \
+                                                       item-title-reflog-forced: 'Force-Push auf %ref (%old → %new)'}}", branch))
+            .unwrap().remove(0);
+        let entries = reflog(&repo, &conf, &url::Url::parse("https://example.org/").unwrap()).unwrap();
+        let mut titles: Vec<_> = entries.iter().map(|e| e.item.title().unwrap().to_string()).collect();
+        titles.sort();
+
+        let short = |id: Oid| id.to_string()[..7].to_string();
+        assert_eq!(titles, [
+            format!("Force-Push auf {} ({} → {})", branch, short(other), short(second)),
+            format!("Reset of {}", branch),
+        ]);
+        assert!(entries.iter().any(|e| e.item.description().unwrap().ends_with("<p>push &lt;forced&gt;</p>")));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+//! Removing scripts and other unsafe HTML from the descriptions and contents
+//! of the items, with an allowlist of elements and attributes
+
+use crate::Entry;
+use std::{collections::HashSet, error};
+use yaml_rust::Yaml;
+
+/// Returns the names of the list in the config entry
+fn names<'a>(value: &'a Yaml, key: &str) -> Result<Vec<&'a str>, Box<dyn error::Error>> {
+    match value {
+        Yaml::Array(list) => list.iter()
+            .map(|x| x.as_str().ok_or_else(|| format!("Invalid value in config entry '{}'", key).into()))
+            .collect(),
+        _ => Err(format!("Invalid value of config entry '{}': expected a list", key).into()),
+    }
+}
+
+/// Returns the sanitizer of the config entry `sanitize-html` or `None`, if it's
+/// missing or false; `tags` replaces the default list of elements and
+/// `attributes` adds attributes to the elements
+pub fn from_conf(conf: &Yaml) -> Result<Option<ammonia::Builder<'_>>, Box<dyn error::Error>> {
+    let mut builder = ammonia::Builder::default();
+    // the changes of item-diff
+    builder.add_tag_attributes("pre", &["class"])
+        .add_tag_attributes("ins", &["style"])
+        .add_tag_attributes("del", &["style"]);
+
+    match conf {
+        Yaml::Boolean(true) => {}
+        Yaml::Hash(_) => {
+            if !conf["tags"].is_badvalue() {
+                builder.tags(names(&conf["tags"], "sanitize-html.tags")?.into_iter().collect::<HashSet<_>>());
+            }
+            match &conf["attributes"] {
+                Yaml::Hash(map) => for (tag, attrs) in map {
+                    let tag = tag.as_str().ok_or("Invalid element in config entry 'sanitize-html.attributes'")?;
+                    builder.add_tag_attributes(tag, names(attrs, "sanitize-html.attributes")?);
+                },
+                Yaml::BadValue => {}
+                _ => return Err("Invalid value of config entry 'sanitize-html.attributes': expected a map".into()),
+            }
+        }
+        Yaml::Boolean(false) | Yaml::BadValue => return Ok(None),
+        _ => return Err("Invalid value of config entry 'sanitize-html'".into()),
+    }
+
+    Ok(Some(builder))
+}
+
+/// Sanitizes the descriptions and contents of the entries
+pub fn apply(builder: &ammonia::Builder, entries: &mut [Entry]) {
+    for e in entries {
+        if let Some(html) = e.item.description() {
+            let html = builder.clean(html).to_string();
+            e.item.set_description(html);
+        }
+        if let Some(html) = e.item.content() {
+            let html = builder.clean(html).to_string();
+            e.item.set_content(html);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conf(text: &str) -> Yaml {
+        yaml_rust::YamlLoader::load_from_str(text).unwrap().remove(0)
+    }
+
+    fn entry(description: &str) -> Entry {
+        let mut item = rss::Item::default();
+        item.set_description(description.to_string());
+        item.set_content(description.to_string());
+        Entry {
+            time: git2::Time::new(0, 0),
+            commit: git2::Oid::zero(),
+            status: git2::Delta::Added,
+            path: String::new(),
+            page: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            author_uri: None,
+            item,
+        }
+    }
+
+    #[test]
+    fn allowlist() {
+        assert!(from_conf(&Yaml::BadValue).unwrap().is_none());
+        assert!(from_conf(&Yaml::Boolean(false)).unwrap().is_none());
+        assert!(from_conf(&Yaml::Integer(1)).is_err());
+        assert!(from_conf(&conf("{tags: p}")).is_err());
+        assert!(from_conf(&conf("{attributes: [img]}")).is_err());
+
+        let html = "<p onclick=\"x()\">A<script>alert(1)</script></p><pre class=\"diff\"><ins style=\"color: green\">B</ins></pre>";
+        let builder = from_conf(&Yaml::Boolean(true)).unwrap().unwrap();
+        let mut entries = vec![entry(html)];
+        apply(&builder, &mut entries);
+        let clean = "<p>A</p><pre class=\"diff\"><ins style=\"color: green\">B</ins></pre>";
+        assert_eq!(entries[0].item.description(), Some(clean));
+        assert_eq!(entries[0].item.content(), Some(clean));
+
+        let allowlist = conf("{tags: [p, img], attributes: {p: [class]}}");
+        let builder = from_conf(&allowlist).unwrap().unwrap();
+        assert_eq!(builder.clean("<p class=\"x\"><b>A</b><img src=\"a.png\"></p>").to_string(),
+                   "<p class=\"x\">A<img src=\"a.png\"></p>");
+    }
+}
@@ -0,0 +1,846 @@
+//! HTTP server that generates the feeds on request
+
+use crate::{generate, output, tune_odb, Params};
+use log::{debug, info, warn};
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    env,
+    error,
+    fs,
+    io::{self, Write},
+    net::IpAddr,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tiny_http::{Header, Method, Request, Response};
+use yaml_rust::{Yaml, YamlLoader};
+
+/// The number of threads that answer the requests waiting for a generation
+const WAITERS: usize = 4;
+
+/// A generated file
+struct Cached {
+    content_type: &'static str,
+    data: Vec<u8>,
+}
+
+/// The generated files by URL path
+type Files = HashMap<String, Cached>;
+
+/// A token bucket for rate limiting
+#[derive(Clone)]
+struct Bucket {
+    /// tokens per second
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    /// Reads the entries `rate` (requests per second) and `burst` (default:
+    /// the rate, but at least 1)
+    fn from_conf(conf: &Yaml, name: &str) -> Result<Option<Self>, Box<dyn error::Error>> {
+        let number = |x: &Yaml| x.as_f64().or_else(|| x.as_i64().map(|x| x as f64));
+        let rate = match &conf["rate"] {
+            Yaml::BadValue => return Ok(None),
+            x => number(x).filter(|x| *x > 0.0)
+                .ok_or_else(|| format!("Invalid value of config entry 'server.rate-limit.{}.rate'", name))?,
+        };
+        let burst = match &conf["burst"] {
+            Yaml::BadValue => rate.max(1.0),
+            x => number(x).filter(|x| *x >= 1.0)
+                .ok_or_else(|| format!("Invalid value of config entry 'server.rate-limit.{}.burst'", name))?,
+        };
+        Ok(Some(Bucket { rate, burst, tokens: burst, last: Instant::now() }))
+    }
+
+    /// Takes a token or returns the time until the next token
+    fn take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * self.rate).min(self.burst);
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.tokens + self.last.elapsed().as_secs_f64() * self.rate >= self.burst
+    }
+}
+
+/// Rate limits for each client and for all clients together
+struct RateLimit {
+    client: Option<Bucket>,
+    clients: HashMap<IpAddr, Bucket>,
+    global: Option<Bucket>,
+}
+
+impl RateLimit {
+    fn from_conf(conf: &Yaml) -> Result<Self, Box<dyn error::Error>> {
+        Ok(RateLimit {
+            client: Bucket::from_conf(&conf["client"], "client")?,
+            clients: HashMap::new(),
+            global: Bucket::from_conf(&conf["global"], "global")?,
+        })
+    }
+
+    /// Takes a token for the client or returns the time until the next
+    /// request is allowed
+    fn check(&mut self, client: Option<IpAddr>) -> Result<(), Duration> {
+        if let (Some(template), Some(client)) = (&self.client, client) {
+            if self.clients.len() > 10_000 {
+                // forget the clients that are idle for long
+                self.clients.retain(|_, x| !x.is_full());
+            }
+            self.clients.entry(client).or_insert_with(|| template.clone()).take()?;
+        }
+
+        match self.global {
+            Some(ref mut bucket) => bucket.take(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The feeds of one config, which are generated on the first request and
+/// kept for the TTL
+struct Site {
+    /// the URL paths of the route; `None` for all paths
+    route: Option<Regex>,
+    conf: Yaml,
+    params: Params,
+    ttl: Duration,
+}
+
+impl Site {
+    /// Reads an entry of the config entry `server.routes`; the config of the
+    /// route gets one output for the path of the route
+    fn from_route(route: &Yaml, params: &Params, ttl: Duration) -> Result<Self, Box<dyn error::Error>> {
+        let path = route["path"].as_str().ok_or("Config entry 'server.routes.path' is missing")?;
+        let file = route["config"].as_str().ok_or("Config entry 'server.routes.config' is missing")?;
+        let mut conf = YamlLoader::load_from_str(&fs::read_to_string(file)?)?.pop()
+            .ok_or_else(|| format!("Empty config file {}", file))?;
+        tune_odb(&conf["odb"])?;
+
+        let mut out = yaml_rust::yaml::Hash::new();
+        out.insert(Yaml::from_str("format"), Yaml::from_str(route["format"].as_str().unwrap_or("rss")));
+        out.insert(Yaml::from_str("file"), Yaml::String(path.trim_start_matches('/').to_string()));
+        if let Some(url) = route["url"].as_str() {
+            out.insert(Yaml::from_str("url"), Yaml::String(url.to_string()));
+        }
+        match conf {
+            Yaml::Hash(ref mut h) => {
+                h.insert(Yaml::from_str("outputs"), Yaml::Array(vec![Yaml::Hash(out)]));
+            }
+            _ => return Err(format!("Invalid config file {}", file).into()),
+        }
+
+        // placeholders like {author} match one path segment
+        let pattern = Regex::new(r"\\\{[a-z]+\\\}")?.replace_all(&regex::escape(path), "[^/]+").into_owned();
+        let paths = match &route["paths"] {
+            Yaml::Array(list) => list.iter()
+                .map(|x| x.as_str().map(str::to_string).ok_or("Invalid value in config entry 'server.routes.paths'"))
+                .collect::<Result<_, _>>()?,
+            Yaml::BadValue => params.paths.clone(),
+            _ => return Err("Invalid value of config entry 'server.routes.paths'".into()),
+        };
+
+        info!("Route {} to config {}", path, file);
+        Ok(Site {
+            route: Some(Regex::new(&format!("^{}$", pattern))?),
+            conf,
+            params: Params { paths, prefix: None, ..params.clone() },
+            ttl,
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        self.route.as_ref().is_none_or(|x| x.is_match(path))
+    }
+
+    /// Returns the settings for the query with the parameters `path`, `limit`,
+    /// `since` and `format`, of which only those in `allowed` may be used
+    fn params(&self, query: &[(String, String)], allowed: &[String]) -> Result<Params, String> {
+        let mut params = self.params.clone();
+
+        for (key, value) in query {
+            if !allowed.contains(key) {
+                return Err(format!("Query parameter {} is not allowed", key));
+            }
+            let invalid = || format!("Invalid value of query parameter {}: {}", key, value);
+
+            match key.as_str() {
+                "path" => params.filter = Some(value.clone()),
+                "limit" => params.limit = Some(value.parse().map_err(|_| invalid())?),
+                "since" => {
+                    let time = chrono::DateTime::parse_from_rfc3339(value).map(|x| x.timestamp())
+                        .or_else(|_| chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                                 .map(|x| x.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp()))
+                        .map_err(|_| invalid())?;
+                    params.since = Some(time);
+                }
+                "format" => params.format = Some(output::Format::from_name(value)
+                                                 .filter(|x| *x != output::Format::Template)
+                                                 .ok_or_else(invalid)?),
+                _ => return Err(format!("Unknown query parameter {}", key)),
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// Generates all files for the settings
+    fn generate(&self, params: &Params) -> Result<Files, Box<dyn error::Error>> {
+        let mut files = HashMap::new();
+        for feed in generate(&self.conf, params)? {
+            let path = match feed.output.file {
+                Some(ref file) => format!("/{}", output::expand_path(file, &feed.vars)?),
+                None => "/".to_string(),
+            };
+            debug!("Caching {}", path);
+            files.insert(path, Cached {
+                content_type: output::content_type(&feed.output),
+                data: feed.data,
+            });
+        }
+        Ok(files)
+    }
+}
+
+/// The files of a site for a query
+struct Slot {
+    params: Params,
+    /// the time of the last generation and its files or error
+    files: Option<(Instant, Result<Arc<Files>, String>)>,
+    /// the generation is queued or running
+    pending: bool,
+}
+
+/// A generation for the worker: the index of the site and the key of the
+/// query
+type Job = (usize, String);
+
+/// The generated files of all sites, shared by the request loop, the waiters
+/// and the worker, which generates them one after another
+struct Cache {
+    /// the slots by index of the site and key of the query
+    slots: Mutex<HashMap<Job, Slot>>,
+    /// signalled after each generation
+    ready: Condvar,
+    jobs: Mutex<mpsc::Sender<Job>>,
+}
+
+impl Cache {
+    /// Starts the worker for the sites
+    fn start(sites: Arc<Vec<Site>>) -> Arc<Self> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let cache = Arc::new(Cache {
+            slots: Mutex::new(HashMap::new()),
+            ready: Condvar::new(),
+            jobs: Mutex::new(sender),
+        });
+
+        let shared = cache.clone();
+        thread::spawn(move || {
+            for job in receiver {
+                let params = match shared.slots.lock().unwrap().get(&job) {
+                    Some(slot) => slot.params.clone(),
+                    None => continue,
+                };
+
+                info!("Generating the feeds for query {:?} of route {}", job.1, job.0);
+                // a panic mustn't leave the waiting requests hanging
+                let result = panic::catch_unwind(AssertUnwindSafe(|| sites[job.0].generate(&params)))
+                    .unwrap_or_else(|_| Err("panic during the generation".into()))
+                    .map(Arc::new)
+                    .map_err(|err| err.to_string());
+                if let Err(ref err) = result {
+                    warn!("Failed to generate the feeds: {}", err);
+                }
+
+                if let Some(slot) = shared.slots.lock().unwrap().get_mut(&job) {
+                    slot.files = Some((Instant::now(), result));
+                    slot.pending = false;
+                }
+                shared.ready.notify_all();
+            }
+        });
+
+        cache
+    }
+
+    /// Hands the slot to the worker, if it isn't already queued
+    fn queue(&self, job: &Job, slot: &mut Slot) {
+        if !slot.pending {
+            slot.pending = true;
+            if self.jobs.lock().unwrap().send(job.clone()).is_err() {
+                warn!("The worker for the feeds has stopped");
+            }
+        }
+    }
+
+    /// Returns the files of the slot, if they are generated; expired files are
+    /// still returned, while the worker generates them again, and new queries
+    /// get queued
+    fn files(&self, job: Job, params: Params, ttl: Duration) -> Option<Arc<Files>> {
+        let mut slots = self.slots.lock().unwrap();
+        if !slots.contains_key(&job) && slots.len() >= 100 {
+            // many different queries
+            slots.retain(|_, x| x.pending || x.files.as_ref().is_some_and(|(time, _)| time.elapsed() <= ttl));
+            if slots.len() >= 100 {
+                slots.retain(|_, x| x.pending);
+            }
+        }
+
+        let slot = slots.entry(job.clone()).or_insert_with(|| Slot { params, files: None, pending: false });
+        match slot.files {
+            Some((time, Ok(ref files))) => {
+                let files = files.clone();
+                if time.elapsed() > ttl {
+                    self.queue(&job, slot);
+                }
+                Some(files)
+            }
+            // a failed generation is retried with the next request
+            _ => {
+                self.queue(&job, slot);
+                None
+            }
+        }
+    }
+
+    /// Waits for the generation of the slot
+    fn wait(&self, job: &Job) -> Result<Arc<Files>, String> {
+        let slots = self.slots.lock().unwrap();
+        let slots = self.ready.wait_while(slots, |x| x.get(job).is_some_and(|slot| slot.pending)).unwrap();
+        match slots.get(job).and_then(|slot| slot.files.as_ref()) {
+            Some((_, result)) => result.clone(),
+            None => Err("The cached feeds were dropped".to_string()),
+        }
+    }
+
+    /// Generates all cached files again, e.g. after a push; the old ones are
+    /// served until then
+    fn refresh(&self) {
+        let mut slots = self.slots.lock().unwrap();
+        for (job, slot) in slots.iter_mut() {
+            self.queue(job, slot);
+        }
+    }
+}
+
+/// CORS headers for requests from other origins
+struct Cors {
+    /// the allowed origins; `*` for all
+    origins: Vec<String>,
+    methods: String,
+}
+
+impl Cors {
+    fn from_conf(conf: &Yaml) -> Result<Option<Self>, Box<dyn error::Error>> {
+        let list = |key: &str, default: &[&str]| match &conf[key] {
+            Yaml::Array(list) => list.iter()
+                .map(|x| x.as_str().map(str::to_string)
+                     .ok_or_else(|| format!("Invalid value in config entry 'server.cors.{}'", key)))
+                .collect::<Result<Vec<_>, _>>(),
+            Yaml::String(x) => Ok(vec![x.clone()]),
+            Yaml::BadValue => Ok(default.iter().map(|x| x.to_string()).collect()),
+            _ => Err(format!("Invalid value of config entry 'server.cors.{}'", key)),
+        };
+
+        if conf.is_badvalue() {
+            return Ok(None);
+        }
+        Ok(Some(Cors {
+            origins: list("origins", &["*"])?,
+            methods: list("methods", &["GET", "HEAD"])?.join(", "),
+        }))
+    }
+
+    /// Returns the headers for the request, if its origin is allowed
+    fn headers(&self, request: &Request) -> Vec<Header> {
+        let Some(origin) = header(request, "Origin") else {
+            return Vec::new();
+        };
+
+        let allowed = if self.origins.iter().any(|x| x == "*") {
+            "*"
+        } else if self.origins.iter().any(|x| x == origin) {
+            origin
+        } else {
+            return Vec::new();
+        };
+
+        let mut headers = vec![
+            Header::from_bytes("Access-Control-Allow-Origin", allowed).unwrap(),
+            Header::from_bytes("Vary", "Origin").unwrap(),
+        ];
+        if *request.method() == Method::Options {
+            headers.push(Header::from_bytes("Access-Control-Allow-Methods", self.methods.as_str()).unwrap());
+            if let Some(x) = header(request, "Access-Control-Request-Headers") {
+                headers.push(Header::from_bytes("Access-Control-Allow-Headers", x).unwrap());
+            }
+            headers.push(Header::from_bytes("Access-Control-Max-Age", "86400").unwrap());
+        }
+        headers
+    }
+}
+
+/// Returns the value of the header of the request
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str())
+}
+
+/// Format of the access log
+enum LogFormat {
+    /// `key=value` pairs
+    Structured,
+    /// the Combined Log Format of Apache and nginx
+    Combined,
+}
+
+/// Log of the requests to the log target `access` or to a file
+struct AccessLog {
+    format: LogFormat,
+    file: Option<fs::File>,
+}
+
+impl AccessLog {
+    fn from_conf(conf: &Yaml) -> Result<Option<Self>, Box<dyn error::Error>> {
+        let format = match conf["format"].as_str() {
+            _ if conf.is_badvalue() || conf.as_bool() == Some(false) => return Ok(None),
+            None | Some("structured") => LogFormat::Structured,
+            Some("combined") => LogFormat::Combined,
+            Some(x) => return Err(format!("Invalid value of config entry 'server.access-log.format': {}", x).into()),
+        };
+        let file = conf["file"].as_str()
+            .map(|path| fs::OpenOptions::new().create(true).append(true).open(path)
+                 .map_err(|err| format!("Failed to open access log {}: {}", path, err)))
+            .transpose()?;
+        Ok(Some(AccessLog { format, file }))
+    }
+
+    /// Returns the line of the request with the status and the size of the
+    /// response
+    fn line(&self, request: &Request, status: u16, bytes: Option<usize>) -> String {
+        let client = request.remote_addr().map_or_else(|| "-".to_string(), |x| x.ip().to_string());
+        let agent = header(request, "User-Agent").unwrap_or("-");
+        let quote = |x: &str| x.replace('\\', "\\\\").replace('"', "\\\"");
+
+        match self.format {
+            LogFormat::Combined => format!(
+                "{} - - [{}] \"{} {} HTTP/{}\" {} {} \"{}\" \"{}\"",
+                client,
+                chrono::Local::now().format("%d/%b/%Y:%H:%M:%S %z"),
+                request.method(),
+                quote(request.url()),
+                request.http_version(),
+                status,
+                bytes.map_or_else(|| "-".to_string(), |x| x.to_string()),
+                quote(header(request, "Referer").unwrap_or("-")),
+                quote(agent),
+            ),
+            LogFormat::Structured => format!(
+                "client={} method={} path=\"{}\" status={} bytes={} user_agent=\"{}\"",
+                client,
+                request.method(),
+                quote(request.url()),
+                status,
+                bytes.unwrap_or(0),
+                quote(agent),
+            ),
+        }
+    }
+
+    fn log(&mut self, request: &Request, status: u16, bytes: Option<usize>) {
+        let line = self.line(request, status, bytes);
+        match self.file {
+            Some(ref mut file) => {
+                if let Err(err) = writeln!(file, "{}", line) {
+                    warn!("Failed to write the access log: {}", err);
+                }
+            }
+            None => info!(target: "access", "{}", line),
+        }
+    }
+}
+
+type Reply = Response<io::Cursor<Vec<u8>>>;
+
+fn status(code: u16) -> Reply {
+    Response::from_data(Vec::new()).with_status_code(code)
+}
+
+/// Returns the response with the file
+fn reply(files: &Files, path: &str) -> Reply {
+    match files.get(path) {
+        Some(file) => {
+            let header = Header::from_bytes("Content-Type", file.content_type).unwrap();
+            Response::from_data(file.data.clone()).with_header(header)
+        }
+        None => status(404),
+    }
+}
+
+/// Sends the responses with the CORS headers and logs them
+struct Responder {
+    cors: Option<Cors>,
+    access_log: Option<Mutex<AccessLog>>,
+}
+
+impl Responder {
+    fn send(&self, request: Request, mut response: Reply) {
+        if let Some(ref cors) = self.cors {
+            for header in cors.headers(&request) {
+                response.add_header(header);
+            }
+        }
+
+        if let Some(ref log) = self.access_log {
+            log.lock().unwrap().log(&request, response.status_code().0, response.data_length());
+        }
+
+        if let Err(err) = request.respond(response) {
+            debug!("Failed to send the response: {}", err);
+        }
+    }
+}
+
+/// A request for the URL path, which waits for the generation of the job
+type Waiting = (Request, Job, String);
+
+/// Starts the threads that answer the requests waiting for a generation, so
+/// that they don't block the others
+fn start_waiters(cache: Arc<Cache>, responder: Arc<Responder>) -> mpsc::Sender<Waiting> {
+    let (sender, receiver) = mpsc::channel::<Waiting>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..WAITERS {
+        let (cache, responder, receiver) = (cache.clone(), responder.clone(), receiver.clone());
+        thread::spawn(move || loop {
+            let next = receiver.lock().unwrap().recv();
+            let Ok((request, job, path)) = next else {
+                break;
+            };
+            let response = match cache.wait(&job) {
+                Ok(files) => reply(&files, &path),
+                Err(_) => status(500),
+            };
+            responder.send(request, response);
+        });
+    }
+    sender
+}
+
+/// The answer to a request
+enum Answer {
+    Now(Reply),
+    /// the file of the URL path after the generation of the job
+    Later(Job, String),
+}
+
+/// The state of the server
+struct Server {
+    sites: Arc<Vec<Site>>,
+    cache: Arc<Cache>,
+    rate_limit: RateLimit,
+    /// the allowed query parameters
+    query: Vec<String>,
+    webhook: Option<String>,
+    webhook_token: String,
+    cors: bool,
+}
+
+impl Server {
+    fn handle(&mut self, request: &Request) -> Answer {
+        let path = request.url().split('?').next().unwrap_or_default();
+
+        if let Err(wait) = self.rate_limit.check(request.remote_addr().map(|x| x.ip())) {
+            debug!("Rate limit exceeded by {:?}", request.remote_addr());
+            let retry = Header::from_bytes("Retry-After", (wait.as_secs() + 1).to_string()).unwrap();
+            return Answer::Now(status(429).with_header(retry));
+        }
+
+        if Some(path) == self.webhook.as_deref() && *request.method() == Method::Post {
+            let token = Some(self.webhook_token.as_str());
+            if ![header(request, "X-Gitlab-Token"), header(request, "X-Webhook-Token")].contains(&token) {
+                warn!("Webhook with invalid token from {:?}", request.remote_addr());
+                return Answer::Now(status(403));
+            }
+
+            info!("Webhook received, generating the cached feeds again");
+            self.cache.refresh();
+            return Answer::Now(status(204));
+        }
+
+        match request.method() {
+            Method::Get | Method::Head => {}
+            Method::Options if self.cors => return Answer::Now(status(204)),
+            _ => return Answer::Now(status(405)),
+        }
+
+        let Some(index) = self.sites.iter().position(|x| x.matches(path)) else {
+            return Answer::Now(status(404));
+        };
+
+        let site = &self.sites[index];
+
+        let mut query: Vec<_> = url::form_urlencoded::parse(request.url().split_once('?').unwrap_or_default().1.as_bytes())
+            .into_owned()
+            .collect();
+        query.sort();
+        let params = match site.params(&query, &self.query) {
+            Ok(params) => params,
+            Err(err) => {
+                debug!("Bad request {}: {}", request.url(), err);
+                return Answer::Now(Response::from_string(err + "\n").with_status_code(400));
+            }
+        };
+
+        let key = url::form_urlencoded::Serializer::new(String::new()).extend_pairs(&query).finish();
+        match self.cache.files((index, key.clone()), params, site.ttl) {
+            Some(files) => Answer::Now(reply(&files, path)),
+            None => Answer::Later((index, key), path.to_string()),
+        }
+    }
+}
+
+/// Reads the config entry `server` and serves the feeds of the config, or of
+/// the configs of `server.routes`, until the process gets killed
+pub fn run(conf: Yaml, params: Params) -> Result<(), Box<dyn error::Error>> {
+    let server_conf = conf["server"].clone();
+    let listen = server_conf["listen"].as_str().unwrap_or("127.0.0.1:8080");
+    let ttl = match server_conf["cache-ttl"].as_str() {
+        Some(x) => humantime::parse_duration(x)?,
+        None => Duration::from_secs(300),
+    };
+    let webhook = server_conf["webhook"].as_str().map(str::to_string);
+    let webhook_token = match (&webhook, server_conf["webhook-token-env"].as_str()) {
+        (None, _) => String::new(),
+        (Some(_), Some(var)) => env::var(var).ok().filter(|x| !x.is_empty())
+            .ok_or_else(|| format!("Environment variable {} is not set", var))?,
+        (Some(_), None) => return Err("The config entry 'server.webhook' needs 'server.webhook-token-env'".into()),
+    };
+
+    let sites = match &server_conf["routes"] {
+        Yaml::Array(routes) => routes.iter()
+            .map(|route| Site::from_route(route, &params, ttl))
+            .collect::<Result<Vec<_>, _>>()?,
+        Yaml::BadValue => vec![Site { route: None, conf, params, ttl }],
+        _ => return Err("Invalid value of config entry 'server.routes'".into()),
+    };
+    let sites = Arc::new(sites);
+
+    let responder = Arc::new(Responder {
+        cors: Cors::from_conf(&server_conf["cors"])?,
+        access_log: AccessLog::from_conf(&server_conf["access-log"])?.map(Mutex::new),
+    });
+    let cache = Cache::start(sites.clone());
+    let waiters = start_waiters(cache.clone(), responder.clone());
+    let mut state = Server {
+        sites,
+        cache,
+        rate_limit: RateLimit::from_conf(&server_conf["rate-limit"])?,
+        query: server_conf["query"].as_vec().map_or_else(Vec::new, |list| {
+            list.iter().filter_map(|x| x.as_str().map(str::to_string)).collect()
+        }),
+        webhook,
+        webhook_token,
+        cors: responder.cors.is_some(),
+    };
+
+    let server = tiny_http::Server::http(listen)
+        .map_err(|err| format!("Failed to listen on {}: {}", listen, err))?;
+    info!("Listening on {}", listen);
+
+    for request in server.incoming_requests() {
+        match state.handle(&request) {
+            Answer::Now(response) => responder.send(request, response),
+            Answer::Later(job, path) => {
+                if let Err(mpsc::SendError((request, ..))) = waiters.send((request, job, path)) {
+                    responder.send(request, status(500));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::TestRequest;
+
+    fn params() -> Params {
+        Params {
+            paths: Vec::new(),
+            prefix: None,
+            strict: false,
+            hook: false,
+            pretty: None,
+            filter: None,
+            limit: None,
+            since: None,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn failed_generation() {
+        let conf = YamlLoader::load_from_str("repo: /nonexistent/gitlog2rss\n").unwrap().remove(0);
+        let ttl = Duration::from_secs(60);
+        let cache = Cache::start(Arc::new(vec![Site { route: None, conf, params: params(), ttl }]));
+
+        let job = (0, String::new());
+        assert!(cache.files(job.clone(), params(), ttl).is_none());
+        assert!(cache.wait(&job).is_err());
+        // retried with the next request
+        assert!(cache.files(job.clone(), params(), ttl).is_none());
+        assert!(cache.wait(&job).is_err());
+    }
+
+    #[test]
+    fn webhook_needs_token() {
+        let conf = YamlLoader::load_from_str("server:\n  webhook: /hook\n").unwrap().remove(0);
+        assert!(run(conf, params()).unwrap_err().to_string().contains("webhook-token-env"));
+
+        let conf = YamlLoader::load_from_str(
+            "server:\n  webhook: /hook\n  webhook-token-env: GITLOG2RSS_TEST_UNSET_TOKEN\n"
+        ).unwrap().remove(0);
+        assert!(run(conf, params()).unwrap_err().to_string().contains("GITLOG2RSS_TEST_UNSET_TOKEN"));
+    }
+
+    #[test]
+    fn routes() {
+        let file = env::temp_dir().join(format!("gitlog2rss-route-{}.yaml", std::process::id()));
+        fs::write(&file, "repo: /srv/team\noutputs: [{format: atom}]\n").unwrap();
+        let route = YamlLoader::load_from_str(&format!(
+            "{{path: '/team/{{author}}.xml', config: {}, format: atom}}", file.display()
+        )).unwrap().remove(0);
+        let mut params = params();
+        params.paths = vec!["src/*.md".to_string()];
+
+        let site = Site::from_route(&route, &params, Duration::from_secs(60)).unwrap();
+        assert!(site.matches("/team/bea.xml"));
+        assert!(!site.matches("/team/bea/x.xml"));
+        assert!(!site.matches("/blog.xml"));
+        assert_eq!(site.params.paths, ["src/*.md"]);
+        assert_eq!(site.conf["repo"].as_str(), Some("/srv/team"));
+        let outputs = output::from_conf(&site.conf).unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].file.as_deref(), Some("team/{author}.xml"));
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn rate_limits() {
+        let conf = YamlLoader::load_from_str("client: {rate: 0.001, burst: 2}\nglobal: {rate: 0.001, burst: 3}\n")
+            .unwrap().remove(0);
+        let mut limit = RateLimit::from_conf(&conf).unwrap();
+        let (a, b) = (Some("192.0.2.1".parse().unwrap()), Some("192.0.2.2".parse().unwrap()));
+
+        assert!(limit.check(a).is_ok());
+        assert!(limit.check(a).is_ok());
+        // the next token of the client comes after 1000 s
+        let wait = limit.check(a).unwrap_err();
+        assert!(wait > Duration::from_secs(990) && wait <= Duration::from_secs(1000));
+        assert!(limit.check(b).is_ok());
+        // all clients have used the burst of the global limit
+        assert!(limit.check(b).is_err());
+
+        // the burst defaults to the rate, but at least 1
+        let bucket = Bucket::from_conf(&YamlLoader::load_from_str("rate: 5").unwrap()[0], "client").unwrap().unwrap();
+        assert_eq!(bucket.burst, 5.0);
+        let bucket = Bucket::from_conf(&YamlLoader::load_from_str("rate: 0.5").unwrap()[0], "client").unwrap().unwrap();
+        assert_eq!(bucket.burst, 1.0);
+        assert!(Bucket::from_conf(&Yaml::BadValue, "client").unwrap().is_none());
+        assert!(Bucket::from_conf(&YamlLoader::load_from_str("rate: 0").unwrap()[0], "client").is_err());
+    }
+
+    #[test]
+    fn cors_headers() {
+        let headers = |cors: &Cors, request: TestRequest| cors.headers(&request.into()).iter()
+            .map(|h| format!("{}: {}", h.field, h.value))
+            .collect::<Vec<_>>();
+        let origin = |x: &str| Header::from_bytes("Origin", x).unwrap();
+        let conf = YamlLoader::load_from_str("origins: [https://jo-so.de]").unwrap().remove(0);
+        let cors = Cors::from_conf(&conf).unwrap().unwrap();
+
+        assert!(headers(&cors, TestRequest::new()).is_empty());
+        assert!(headers(&cors, TestRequest::new().with_header(origin("https://example.org"))).is_empty());
+        assert_eq!(headers(&cors, TestRequest::new().with_header(origin("https://jo-so.de"))),
+                   ["Access-Control-Allow-Origin: https://jo-so.de", "Vary: Origin"]);
+        let preflight = TestRequest::new()
+            .with_method(Method::Options)
+            .with_header(origin("https://jo-so.de"))
+            .with_header(Header::from_bytes("Access-Control-Request-Headers", "X-Test").unwrap());
+        assert_eq!(headers(&cors, preflight),
+                   ["Access-Control-Allow-Origin: https://jo-so.de", "Vary: Origin",
+                    "Access-Control-Allow-Methods: GET, HEAD", "Access-Control-Allow-Headers: X-Test",
+                    "Access-Control-Max-Age: 86400"]);
+
+        let conf = YamlLoader::load_from_str("methods: GET").unwrap().remove(0);
+        let cors = Cors::from_conf(&conf).unwrap().unwrap();
+        let preflight = TestRequest::new().with_method(Method::Options).with_header(origin("https://example.org"));
+        assert_eq!(headers(&cors, preflight)[..3],
+                   ["Access-Control-Allow-Origin: *", "Vary: Origin", "Access-Control-Allow-Methods: GET"]);
+        assert!(Cors::from_conf(&Yaml::BadValue).unwrap().is_none());
+    }
+
+    #[test]
+    fn access_log_lines() {
+        let request: Request = TestRequest::new()
+            .with_path("/feed.xml?limit=5")
+            .with_remote_addr("192.0.2.1:4711".parse().unwrap())
+            .with_header(Header::from_bytes("User-Agent", "Feed \"Reader\"").unwrap())
+            .into();
+
+        let conf = YamlLoader::load_from_str("format: structured").unwrap().remove(0);
+        let log = AccessLog::from_conf(&conf).unwrap().unwrap();
+        assert_eq!(log.line(&request, 200, Some(1234)),
+                   r#"client=192.0.2.1 method=GET path="/feed.xml?limit=5" status=200 bytes=1234 user_agent="Feed \"Reader\"""#);
+
+        let conf = YamlLoader::load_from_str("format: combined").unwrap().remove(0);
+        let log = AccessLog::from_conf(&conf).unwrap().unwrap();
+        let line = log.line(&request, 404, None);
+        let re = Regex::new(r#"^192\.0\.2\.1 - - \[\d\d/[A-Z][a-z]{2}/\d{4}:\d\d:\d\d:\d\d [+-]\d{4}\] "GET /feed\.xml\?limit=5 HTTP/1\.1" 404 - "-" "Feed \\"Reader\\"""#).unwrap();
+        assert!(re.is_match(&line), "{}", line);
+
+        assert!(AccessLog::from_conf(&Yaml::BadValue).unwrap().is_none());
+        assert!(AccessLog::from_conf(&Yaml::Boolean(false)).unwrap().is_none());
+        let conf = YamlLoader::load_from_str("format: json").unwrap().remove(0);
+        assert!(AccessLog::from_conf(&conf).is_err());
+    }
+
+    #[test]
+    fn query_params() {
+        let conf = YamlLoader::load_from_str("repo: /srv/blog\n").unwrap().remove(0);
+        let site = Site { route: None, conf, params: params(), ttl: Duration::from_secs(60) };
+        let query = |x: &str| url::form_urlencoded::parse(x.as_bytes()).into_owned().collect::<Vec<_>>();
+        let allowed = ["path", "limit", "since", "format"].map(str::to_string);
+
+        let params = site.params(&query("path=blog/**&limit=5&since=2024-01-01&format=atom"), &allowed).unwrap();
+        assert_eq!(params.filter.as_deref(), Some("blog/**"));
+        assert_eq!(params.limit, Some(5));
+        assert_eq!(params.since, Some(1_704_067_200));
+        assert_eq!(params.format, Some(output::Format::Atom));
+        let params = site.params(&query("since=2024-01-01T01:00:00%2B01:00"), &allowed).unwrap();
+        assert_eq!(params.since, Some(1_704_067_200));
+
+        assert!(site.params(&query("limit=5"), &allowed[..1]).err().unwrap().contains("not allowed"));
+        assert!(site.params(&query("limit=x"), &allowed).is_err());
+        assert!(site.params(&query("since=yesterday"), &allowed).is_err());
+        assert!(site.params(&query("format=template"), &allowed).is_err());
+        assert!(site.params(&query("page=2"), &["page".to_string()]).err().unwrap().contains("Unknown"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Synthesized items summarizing the changes of a week or month
+
+use crate::{expand, html_escape, section, Entry};
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc};
+use git2::{Delta, Oid};
+use rss::{Guid, ItemBuilder};
+use std::{collections::BTreeMap, error};
+use yaml_rust::Yaml;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Returns the first day of the period containing the day
+    fn start(self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => day - Duration::days(day.weekday().num_days_from_monday().into()),
+            Period::Month => day.with_day(1).unwrap(),
+        }
+    }
+
+    /// Returns the first day of the next period
+    fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => start + Duration::days(7),
+            Period::Month if start.month() == 12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1).unwrap(),
+            Period::Month => NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1).unwrap(),
+        }
+    }
+}
+
+/// Returns one item for each complete week or month with changes, as
+/// configured by the config entry `summary`; the items must be sorted by date
+pub fn entries(conf: &Yaml, items: &[Entry], strip_prefix: &str, base_url: &url::Url)
+               -> Result<Vec<Entry>, Box<dyn error::Error>>
+{
+    let period = match conf["period"].as_str() {
+        None => return Ok(Vec::new()),
+        Some("week") => Period::Week,
+        Some("month") => Period::Month,
+        Some(x) => return Err(format!("Invalid value of config entry 'summary.period': {}", x).into()),
+    };
+    let title = conf["title"].as_str().unwrap_or("Summary %s – %e");
+    let description = conf["description"].as_str()
+        .unwrap_or("%n new, %m updated and %r removed pages; most changes in %d");
+
+    let mut groups = BTreeMap::<NaiveDate, Vec<&Entry>>::new();
+    for e in items {
+        let day = Utc.timestamp_opt(e.time.seconds(), 0).unwrap().date_naive();
+        groups.entry(period.start(day)).or_default().push(e);
+    }
+
+    let today = Utc::now().date_naive();
+    let mut summaries = Vec::new();
+    for (start, group) in groups {
+        let end = period.next(start);
+        if end > today {
+            // the period is not over
+            continue;
+        }
+
+        let count = |status| group.iter().filter(|e| e.status == status).count().to_string();
+
+        let mut dirs = BTreeMap::<&str, usize>::new();
+        for e in &group {
+            *dirs.entry(section(&e.path, strip_prefix)).or_default() += 1;
+        }
+        let mut dirs: Vec<_> = dirs.into_iter().collect();
+        dirs.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        let vars = [
+            ("s", start.to_string()),
+            ("e", (end - Duration::days(1)).to_string()),
+            ("n", count(Delta::Added)),
+            ("m", count(Delta::Modified)),
+            ("r", count(Delta::Deleted)),
+            ("d", dirs.iter().take(3).map(|(dir, _)| *dir).collect::<Vec<_>>().join(", ")),
+        ];
+        let html_vars: Vec<_> = vars.iter().map(|(k, v)| (*k, html_escape(v))).collect();
+
+        let time = Utc.from_utc_datetime(&end.and_hms_opt(0, 0, 0).unwrap());
+        let id = Oid::hash_object(git2::ObjectType::Blob, format!("summary:{}", start).as_bytes())?;
+
+        summaries.push(Entry {
+            time: git2::Time::new(time.timestamp(), 0),
+            commit: Oid::zero(),
+            status: Delta::Unmodified,
+            path: String::new(),
+            page: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            author_uri: None,
+            item: ItemBuilder::default()
+                .pub_date(Some(time.to_rfc2822()))
+                .title(Some(expand(title, &vars)))
+                .link(Some(base_url.to_string()))
+                .description(Some(expand(description, &html_vars)))
+                .guid(Some(Guid { value: id.to_string(), permalink: false }))
+                .build(),
+        });
+    }
+
+    Ok(summaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn entry(day: &str, status: Delta, path: &str) -> Entry {
+        let time = NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap().and_hms_opt(12, 0, 0).unwrap();
+        Entry {
+            time: git2::Time::new(time.and_utc().timestamp(), 0),
+            commit: Oid::zero(),
+            status,
+            path: path.to_string(),
+            page: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            author_uri: None,
+            item: Default::default(),
+        }
+    }
+
+    #[test]
+    fn periods() {
+        let day = |x| NaiveDate::parse_from_str(x, "%Y-%m-%d").unwrap();
+        assert_eq!(Period::Week.start(day("2024-03-07")), day("2024-03-04"));
+        assert_eq!(Period::Week.next(day("2024-03-04")), day("2024-03-11"));
+        assert_eq!(Period::Month.start(day("2024-12-24")), day("2024-12-01"));
+        assert_eq!(Period::Month.next(day("2024-12-01")), day("2025-01-01"));
+    }
+
+    #[test]
+    fn weekly() {
+        let conf = YamlLoader::load_from_str("{period: week, title: 'Woche %s – %e', \
+                                              description: '%n neu, %m bearbeitet, %r gelöscht in %d'}")
+            .unwrap().remove(0);
+        let items = [
+            entry("2024-03-04", Delta::Added, "src/blog/a.md"),
+            entry("2024-03-05", Delta::Modified, "src/blog/a.md"),
+            entry("2024-03-06", Delta::Deleted, "src/<x>/b.md"),
+            entry("2024-03-12", Delta::Added, "src/c.md"),
+        ];
+        let base_url = url::Url::parse("https://example.org/").unwrap();
+
+        let summaries = entries(&conf, &items, "src/", &base_url).unwrap();
+        assert_eq!(summaries.len(), 2);
+        let item = &summaries[0].item;
+        assert_eq!(item.title(), Some("Woche 2024-03-04 – 2024-03-10"));
+        assert_eq!(item.description(), Some("1 neu, 1 bearbeitet, 1 gelöscht in blog, &lt;x&gt;"));
+        assert_eq!(summaries[1].item.description(), Some("1 neu, 0 bearbeitet, 0 gelöscht in root"));
+
+        assert!(entries(&Yaml::BadValue, &items, "src/", &base_url).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+//! Tera templates for the titles and descriptions of the items, see
+//! https://keats.github.io/tera/docs/
+
+use crate::html_escape;
+use std::error;
+use yaml_rust::Yaml;
+
+const TITLE: &str = "title";
+/// the suffix enables the escaping of the values
+const DESCRIPTION: &str = "description.html";
+
+/// The templates of the config entries `item-title-template` and
+/// `item-description-template`
+pub struct Templates {
+    tera: tera::Tera,
+    title: bool,
+    description: bool,
+}
+
+impl Templates {
+    pub fn from_conf(conf: &Yaml) -> Result<Self, Box<dyn error::Error>> {
+        let mut tera = tera::Tera::default();
+        // Tera escapes also / and '
+        tera.set_escape_fn(html_escape);
+
+        let mut add = |key: &str, name: &str| match &conf[key] {
+            Yaml::String(text) => tera.add_raw_template(name, text)
+                .map(|_| true)
+                .map_err(|err| format!("Invalid value of config entry '{}': {:?}", key, err)),
+            Yaml::BadValue => Ok(false),
+            _ => Err(format!("Invalid value of config entry '{}'", key)),
+        };
+        let title = add("item-title-template", TITLE)?;
+        let description = add("item-description-template", DESCRIPTION)?;
+
+        Ok(Templates { tera, title, description })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.title && !self.description
+    }
+
+    /// Returns the title of the template or `None` without one
+    pub fn title(&self, ctx: &tera::Context) -> Result<Option<String>, Box<dyn error::Error>> {
+        if !self.title {
+            return Ok(None);
+        }
+        Ok(Some(self.tera.render(TITLE, ctx)?.trim().to_string()))
+    }
+
+    /// Returns the HTML of the description of the template or `None` without
+    /// one
+    pub fn description(&self, ctx: &tera::Context) -> Result<Option<String>, Box<dyn error::Error>> {
+        if !self.description {
+            return Ok(None);
+        }
+        Ok(Some(self.tera.render(DESCRIPTION, ctx)?.trim().to_string()).filter(|x| !x.is_empty()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conf(text: &str) -> Yaml {
+        yaml_rust::YamlLoader::load_from_str(text).unwrap().remove(0)
+    }
+
+    #[test]
+    fn render() {
+        let templates = Templates::from_conf(&conf("
+item-title-template: ' {{ path }} & {{ stats.added }} '
+item-description-template: '<p>{{ path }}</p>'
+")).unwrap();
+        assert!(!templates.is_empty());
+        let ctx = tera::Context::from_value(serde_json::json!({"path": "a&b/c.md", "stats": {"added": 2}})).unwrap();
+        assert_eq!(templates.title(&ctx).unwrap().as_deref(), Some("a&b/c.md & 2"));
+        assert_eq!(templates.description(&ctx).unwrap().as_deref(), Some("<p>a&amp;b/c.md</p>"));
+
+        let templates = Templates::from_conf(&conf("item-description-template: '{% if path %}x{% endif %}'")).unwrap();
+        assert_eq!(templates.title(&ctx).unwrap(), None);
+        assert_eq!(templates.description(&tera::Context::new()).unwrap(), None);
+
+        assert!(Templates::from_conf(&conf("item-title-template: '{{ path'")).is_err());
+        assert!(Templates::from_conf(&conf("item-title-template: [1]")).is_err());
+        assert!(Templates::from_conf(&conf("{}")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+//! Post-processing of the XML written by the rss crate
+
+use std::{convert::TryFrom, error};
+use yaml_rust::Yaml;
+
+/// The character encoding of the output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    /// characters outside of Latin-1 become character references
+    Latin1,
+}
+
+/// Options from the config for the written XML
+#[derive(Debug)]
+pub struct Options {
+    pub declaration: bool,
+    /// the value of the attribute `standalone` of the declaration
+    pub standalone: Option<bool>,
+    pub encoding: Encoding,
+    /// `Some(true)` wraps all HTML content in CDATA sections, `Some(false)`
+    /// escapes it, and `None` keeps the choice of the rss crate
+    pub cdata: Option<bool>,
+    /// the URL of the stylesheet for `<?xml-stylesheet?>`
+    pub stylesheet: Option<String>,
+}
+
+impl Options {
+    pub fn from_conf(conf: &Yaml) -> Result<Self, Box<dyn error::Error>> {
+        let encoding = match conf["output-encoding"].as_str().map(str::to_ascii_lowercase).as_deref() {
+            None | Some("utf-8" | "utf8") => Encoding::Utf8,
+            Some("iso-8859-1" | "latin1" | "latin-1") => Encoding::Latin1,
+            Some(_) => return Err(format!(
+                "Invalid value of config entry 'output-encoding': {}",
+                conf["output-encoding"].as_str().unwrap()
+            ).into()),
+        };
+
+        let declaration = match &conf["xml-declaration"] {
+            Yaml::Boolean(x) => *x,
+            Yaml::BadValue => true,
+            _ => return Err("Invalid value of config entry 'xml-declaration'".into()),
+        };
+
+        let standalone = match &conf["xml-standalone"] {
+            Yaml::Boolean(x) => Some(*x),
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'xml-standalone'".into()),
+        };
+
+        let cdata = match &conf["html-cdata"] {
+            Yaml::Boolean(x) => Some(*x),
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'html-cdata'".into()),
+        };
+
+        let stylesheet = match &conf["xml-stylesheet"] {
+            Yaml::String(x) => Some(x.clone()),
+            Yaml::BadValue => None,
+            _ => return Err("Invalid value of config entry 'xml-stylesheet'".into()),
+        };
+
+        Ok(Options { declaration, standalone, encoding, cdata, stylesheet })
+    }
+}
+
+/// Applies the options to the XML document `xml`
+pub fn finish(xml: &str, opts: &Options) -> Vec<u8> {
+    let has_decl = xml.starts_with("<?xml ");
+    let body = match xml.find("?>") {
+        Some(end) if has_decl => &xml[end + 2..],
+        _ => xml,
+    };
+    let newline = body.starts_with('\n');
+    let body = body.trim_start_matches('\n');
+
+    let mut doc = String::with_capacity(xml.len() + 100);
+    if opts.declaration {
+        doc.push_str("<?xml version=\"1.0\" encoding=\"");
+        doc.push_str(match opts.encoding {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "ISO-8859-1",
+        });
+        match opts.standalone {
+            Some(true) => doc.push_str("\" standalone=\"yes"),
+            Some(false) => doc.push_str("\" standalone=\"no"),
+            None => {}
+        }
+        doc.push_str("\"?>");
+        if !has_decl || newline {
+            doc.push('\n');
+        }
+    }
+    if let Some(ref href) = opts.stylesheet {
+        let mime_type = if href.ends_with(".css") { "text/css" } else { "text/xsl" };
+        if !doc.is_empty() && !doc.ends_with('\n') {
+            doc.push('\n');
+        }
+        doc.push_str(&format!("<?xml-stylesheet type=\"{}\" href=\"{}\"?>\n",
+                              mime_type, crate::html_escape(href)));
+    }
+    match opts.cdata {
+        Some(true) => doc.push_str(&cdata_channel_description(body)),
+        Some(false) => doc.push_str(&escape_cdata(body)),
+        None => doc.push_str(body),
+    }
+
+    match opts.encoding {
+        Encoding::Utf8 => doc.into_bytes(),
+        Encoding::Latin1 => to_latin1(&doc),
+    }
+}
+
+/// Writes the compact XML document `xml` with the indentation; the atom
+/// crate indents only with spaces
+pub fn indent(xml: &[u8], indent_char: u8, indent_size: usize) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    use quick_xml::{events::Event, Reader, Writer};
+
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new_with_indent(Vec::with_capacity(xml.len() * 2), indent_char, indent_size);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Eof => break,
+            event => writer.write_event(event)?,
+        }
+        buf.clear();
+    }
+
+    let mut out = writer.into_inner();
+    out.push(b'\n');
+    Ok(out)
+}
+
+/// Replaces all CDATA sections by escaped text
+fn escape_cdata(doc: &str) -> String {
+    let mut out = String::with_capacity(doc.len());
+    let mut rest = doc;
+
+    while let Some(start) = rest.find("<![CDATA[") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 9..];
+
+        let end = rest.find("]]>").unwrap_or(rest.len());
+        for c in rest[..end].chars() {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                c => out.push(c),
+            }
+        }
+        rest = rest.get(end + 3..).unwrap_or("");
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Wraps the description of the channel in a CDATA section; the descriptions
+/// of the items are already written as CDATA
+fn cdata_channel_description(doc: &str) -> String {
+    let item = doc.find("<item>").unwrap_or(doc.len());
+    let (start, end) = match (doc[..item].find("<description>"), doc[..item].find("</description>")) {
+        (Some(start), Some(end)) if start < end => (start + 13, end),
+        _ => return doc.to_string(),
+    };
+
+    if doc[start..end].starts_with("<![CDATA[") {
+        return doc.to_string();
+    }
+
+    let text = doc[start..end]
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+
+    format!("{}<![CDATA[{}]]>{}", &doc[..start], text.replace("]]>", "]]]]><![CDATA[>"), &doc[end..])
+}
+
+/// Converts the document to ISO-8859-1; all other characters become character
+/// references, which requires to split CDATA sections around them
+fn to_latin1(doc: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(doc.len());
+    let mut in_cdata = false;
+    let mut rest = doc;
+
+    while let Some(c) = rest.chars().next() {
+        if !in_cdata && rest.starts_with("<![CDATA[") {
+            in_cdata = true;
+        } else if in_cdata && rest.starts_with("]]>") {
+            in_cdata = false;
+        }
+
+        match u8::try_from(u32::from(c)) {
+            Ok(b) => out.push(b),
+            Err(_) if in_cdata => {
+                out.extend_from_slice(format!("]]>&#{};<![CDATA[", u32::from(c)).as_bytes())
+            }
+            Err(_) => out.extend_from_slice(format!("&#{};", u32::from(c)).as_bytes()),
+        }
+
+        rest = &rest[c.len_utf8()..];
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(declaration: bool, encoding: Encoding) -> Options {
+        Options { declaration, standalone: None, encoding, cdata: None, stylesheet: None }
+    }
+
+    #[test]
+    fn declaration() {
+        let xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rss/>";
+        assert_eq!(finish(xml, &options(false, Encoding::Utf8)), b"<rss/>");
+        assert_eq!(finish(xml, &options(true, Encoding::Utf8)), xml.as_bytes());
+        assert_eq!(finish(xml, &options(true, Encoding::Latin1)),
+                   b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<rss/>");
+
+        let opts = Options { standalone: Some(true), ..options(true, Encoding::Latin1) };
+        assert_eq!(finish(xml, &opts), b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\" standalone=\"yes\"?>\n<rss/>");
+        let opts = Options { standalone: Some(false), ..options(true, Encoding::Utf8) };
+        assert_eq!(finish("<rss/>", &opts), b"<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"no\"?>\n<rss/>");
+    }
+
+    #[test]
+    fn stylesheet() {
+        let opts = Options { stylesheet: Some("/feed.css?a&b".into()), ..options(true, Encoding::Utf8) };
+        assert_eq!(String::from_utf8(finish("<?xml version=\"1.0\" encoding=\"utf-8\"?><rss/>", &opts)).unwrap(),
+                   "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                    <?xml-stylesheet type=\"text/xsl\" href=\"/feed.css?a&amp;b\"?>\n<rss/>");
+
+        let opts = Options { declaration: false, stylesheet: Some("/feed.css".into()), ..opts };
+        assert_eq!(finish("<rss/>", &opts), b"<?xml-stylesheet type=\"text/css\" href=\"/feed.css\"?>\n<rss/>");
+    }
+
+    #[test]
+    fn latin1() {
+        assert_eq!(to_latin1("<t>Grüße €</t>"), b"<t>Gr\xfc\xdfe &#8364;</t>");
+        assert_eq!(to_latin1("<![CDATA[€]]>"), b"<![CDATA[]]>&#8364;<![CDATA[]]>");
+    }
+
+    #[test]
+    fn cdata() {
+        assert_eq!(escape_cdata("<d><![CDATA[<p>a & b</p>]]></d>"), "<d>&lt;p&gt;a &amp; b&lt;/p&gt;</d>");
+        assert_eq!(escape_cdata("<d>x</d>"), "<d>x</d>");
+
+        let doc = "<channel><description>&lt;b&gt;D&lt;/b&gt;</description><item><description>I</description></item>";
+        assert_eq!(cdata_channel_description(doc),
+                   "<channel><description><![CDATA[<b>D</b>]]></description><item><description>I</description></item>");
+    }
+
+    #[test]
+    fn indent_with_tabs() {
+        let xml = indent(b"<feed><title>T</title><entry><id>x</id></entry></feed>", b'\t', 1).unwrap();
+        assert_eq!(String::from_utf8(xml).unwrap(),
+                   "<feed>\n\t<title>T</title>\n\t<entry>\n\t\t<id>x</id>\n\t</entry>\n</feed>\n");
+    }
+}